@@ -0,0 +1,77 @@
+//! Analog-digital conversion traits.
+//!
+//! With the `eh02` feature, the default, `Channel` and `OneShot` are the embedded-hal 0.2 ADC traits, so the ADC
+//! of your HAL is used as it is. embedded-hal 1.0 dropped them, so with the `eh1` feature `Channel` and `OneShot`
+//! are defined here, mirroring the 0.2 ones, and an ADC from your HAL can be used with the sensor through a small
+//! `impl` or wrapper, see `crate::hal`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! struct SensorAdc(stm32f4xx_hal::adc::Adc<ADC1>);
+//! struct SensorPin(PC0<Analog>);
+//!
+//! impl Channel<SensorAdc> for SensorPin {
+//!     type ID = u8;
+//!     fn channel() -> u8 {
+//!         10
+//!     }
+//! }
+//!
+//! impl OneShot<SensorAdc, u16, SensorPin> for SensorAdc {
+//!     type Error = ();
+//!     fn read(&mut self, pin: &mut SensorPin) -> nb::Result<u16, ()> {
+//!         Ok(self.0.convert(&pin.0, SampleTime::Cycles_480))
+//!     }
+//! }
+//! ```
+
+#[cfg(all(feature = "eh02", not(feature = "eh1")))]
+pub use embedded_hal_02::adc::{Channel, OneShot};
+
+/// A marker trait to identify MCU pins that can be used as inputs to an ADC channel.
+#[cfg(not(all(feature = "eh02", not(feature = "eh1"))))]
+pub trait Channel<Adc> {
+    /// Channel ID type
+    ///
+    /// A type used to identify this ADC channel. For example, if the ADC has eight channels, this
+    /// might be a `u8`. If the ADC has multiple banks of channels, it could be a tuple, like
+    /// `(u8: bank_id, u8: channel_id)`.
+    type ID;
+
+    /// Get the specific ID that identifies this channel, for example `0_u8` for the first ADC
+    /// channel, if Self::ID is u8.
+    fn channel() -> Self::ID;
+}
+
+/// ADCs that sample on single channels per request, and do so at the time of the request.
+#[cfg(not(all(feature = "eh02", not(feature = "eh1"))))]
+pub trait OneShot<Adc, Word, Pin: Channel<Adc>> {
+    /// Error type returned by ADC methods
+    type Error;
+
+    /// Request that the ADC begin a conversion on the specified pin
+    ///
+    /// This method takes a `Pin` reference, as it is expected that the ADC will be able to sample
+    /// whatever channel underlies the pin.
+    fn read(&mut self, pin: &mut Pin) -> nb::Result<Word, Self::Error>;
+}
+
+/// ADCs that can be powered down between measurements to save power.
+///
+/// Implementing this for the one shot reader enables `Gp2y1014au::power_down`, `Gp2y1014au::power_up`
+/// and `Gp2y1014au::read_powered`.
+pub trait PowerControl {
+    /// Powers the ADC up so it is ready for a conversion
+    fn power_up(&mut self);
+
+    /// Powers the ADC down until the next `power_up`
+    fn power_down(&mut self);
+}
+
+/// Placeholder reader for sensors which do not own an ADC, see `Gp2y1014au::new_shared`.
+///
+/// It does not implement `OneShot`, so the methods using the reader owned by the sensor, like `Gp2y1014au::read`,
+/// do not exist and readings have to be taken with `Gp2y1014au::read_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SharedAdc;
@@ -0,0 +1,281 @@
+use core::marker::PhantomData;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::delay::DelayNs;
+use crate::adc::Channel;
+use crate::sensor::{ConfigError, Error, LedPolarity};
+use crate::timing::Timing;
+
+/// Asynchronous single-shot ADC conversion, the async counterpart of `crate::adc::OneShot`.
+///
+/// Instead of returning `nb::Error::WouldBlock`, implementations should await the end of the conversion.
+#[allow(async_fn_in_trait)]
+pub trait OneShot<Adc, Word, Pin: Channel<Adc>> {
+    /// Error type returned by the ADC
+    type Error;
+
+    /// Request that the ADC begin a conversion on the specified pin and wait for the result
+    async fn read(&mut self, pin: &mut Pin) -> Result<Word, Self::Error>;
+}
+
+/// Asynchronous version of `crate::sensor::Gp2y1014au`.
+///
+/// The LED pulse delays and the ADC conversion are awaited, so other tasks can run meanwhile.
+pub struct Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc>,
+    Delay: DelayNs,
+{
+    pin_led: PinLed,
+    one_shot_reader: OneShotReader,
+    pin_data: PinData,
+    delay: Delay,
+    timing: Timing,
+    led_polarity: LedPolarity,
+    pulsing: bool,
+    pulsed: bool,
+    _unused: PhantomData<fn() -> (Adc, Word)>,
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc>,
+    Delay: DelayNs,
+{
+    /// Creates a new instance of the Gp2y1014au dust sensor
+    ///
+    /// # Arguments
+    ///
+    /// * `pin_led`  - The pin connected to the led for the sensor.
+    /// * `pin_data` - The pin connected to data/out on the sensor.
+    /// * `one_shot_reader` - A structure that implements "crate::asynch::OneShot"
+    /// * `delay` - A structure that implements "embedded_hal_async::delay::DelayNs", used to time the LED pulse.
+    pub fn new(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader, delay: Delay) -> Self {
+        Self {
+            pin_led,
+            one_shot_reader,
+            pin_data,
+            delay,
+            timing: Timing::datasheet(),
+            led_polarity: LedPolarity::ActiveLow,
+            pulsing: false,
+            pulsed: false,
+            _unused: PhantomData,
+        }
+    }
+
+    /// Returns the timing of the LED pulse.
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Replaces the timing of the LED pulse, which defaults to `Timing::datasheet`.
+    ///
+    /// Returns the error of `Timing::validate` and keeps the current timing if `timing` is invalid.
+    pub fn set_timing(&mut self, timing: Timing) -> core::result::Result<(), ConfigError> {
+        timing.validate()?;
+        self.timing = timing;
+        Ok(())
+    }
+
+    /// Sets the level of the LED pin which turns the LED on, defaults to `LedPolarity::ActiveLow`.
+    pub fn set_led_polarity(&mut self, led_polarity: LedPolarity) {
+        self.led_polarity = led_polarity;
+    }
+
+    fn led_on(&mut self) -> core::result::Result<(), PinLed::Error> {
+        match self.led_polarity {
+            LedPolarity::ActiveLow => self.pin_led.set_low(),
+            LedPolarity::ActiveHigh => self.pin_led.set_high(),
+        }
+    }
+
+    fn led_off(&mut self) -> core::result::Result<(), PinLed::Error> {
+        match self.led_polarity {
+            LedPolarity::ActiveLow => self.pin_led.set_high(),
+            LedPolarity::ActiveHigh => self.pin_led.set_low(),
+        }
+    }
+
+    /// Reads the pin state. Returns back `Word` which varies based on your HAL implementation.
+    ///
+    /// This behaves like `crate::sensor::Gp2y1014au::read` and returns the same errors,
+    /// except that the delays and the ADC conversion are awaited. As there, the LED is turned off
+    /// before any error is returned, right away if turning it on or the conversion failed.
+    ///
+    /// Consecutive pulses are spaced by `Timing::cycle_period_us`: after the first reading the rest of the
+    /// cycle is awaited before the LED is turned on again, see `reset`.
+    pub async fn read(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.wait_for_cycle().await;
+        match self.led_on() {
+            Ok(()) => (),
+            Err(error) => {
+                let _ = self.led_off();
+                return Err(Error::LedError(error))
+            }
+        };
+        self.pulsing = true;
+        self.delay.delay_us(self.timing.sample_delay_us.into()).await;
+        let result = self.one_shot_reader.read(&mut self.pin_data).await.map_err(Error::ReadError);
+        if result.is_ok() {
+            self.delay.delay_us(self.timing.settling_delay_us().into()).await;
+        }
+        self.pulsing = false;
+        self.led_off().map_err(Error::LedError)?;
+
+        result
+    }
+
+    /// Awaits the rest of the cycle if a pulse was already sent.
+    async fn wait_for_cycle(&mut self) {
+        if self.pulsed {
+            let pulse_width_us: u32 = self.timing.pulse_width_us.into();
+            self.delay.delay_us(self.timing.cycle_period_us.saturating_sub(pulse_width_us)).await;
+        }
+        self.pulsed = true;
+    }
+
+    /// Makes the next `read` pulse right away, for example after waking from deep sleep.
+    pub fn reset(&mut self) {
+        self.pulsed = false;
+    }
+
+    /// Returns back the pins, reader and delay used to construct the sensor.
+    /// This function consumes self.
+    ///
+    /// If a `read` future was dropped during the LED pulse, the LED is turned off first.
+    /// An error turning it off is ignored.
+    pub fn split(mut self) -> (PinLed, PinData, OneShotReader, Delay) {
+        if self.pulsing {
+            let _ = self.led_off();
+        }
+        (self.pin_led, self.pin_data, self.one_shot_reader, self.delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_hal::digital::{ErrorKind, ErrorType, OutputPin};
+    use embedded_hal_async::delay::DelayNs;
+    use crate::adc::Channel;
+
+    struct TestAdc;
+    struct TestFailingAdc;
+    struct TestAnalogPin;
+    struct TestOutputPin {
+        fail: bool,
+        set_high_calls: u32,
+    }
+    struct TestDelay {
+        waited_ns: u32
+    }
+
+    impl ErrorType for TestOutputPin {
+        type Error = ErrorKind;
+    }
+
+    impl OutputPin for TestOutputPin {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set_high_calls += 1;
+            if self.fail { Err(ErrorKind::Other) } else { Ok(()) }
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            if self.fail { Err(ErrorKind::Other) } else { Ok(()) }
+        }
+    }
+
+    impl Channel<TestAdc> for TestAnalogPin {
+        type ID = u8;
+        fn channel() -> Self::ID {
+            1
+        }
+    }
+
+    impl super::OneShot<TestAdc, u16, TestAnalogPin> for TestAdc {
+        type Error = ();
+        async fn read(&mut self, _: &mut TestAnalogPin) -> Result<u16, ()> {
+            Ok(10)
+        }
+    }
+
+    impl super::OneShot<TestAdc, u16, TestAnalogPin> for TestFailingAdc {
+        type Error = ();
+        async fn read(&mut self, _: &mut TestAnalogPin) -> Result<u16, ()> {
+            Err(())
+        }
+    }
+
+    impl DelayNs for TestDelay {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.waited_ns += ns;
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn read_returns_value_after_the_full_led_pulse() {
+        let led_pin = TestOutputPin { fail: false, set_high_calls: 0 };
+        let mut sensor = super::Gp2y1014au::new(led_pin, TestAnalogPin, TestAdc, TestDelay { waited_ns: 0 });
+        assert_eq!(Ok(10), block_on(sensor.read()));
+        let (_, _, _, delay) = sensor.split();
+        assert_eq!(320_000, delay.waited_ns);
+    }
+
+    #[test]
+    fn read_uses_the_configured_timing() {
+        let led_pin = TestOutputPin { fail: false, set_high_calls: 0 };
+        let mut sensor = super::Gp2y1014au::new(led_pin, TestAnalogPin, TestAdc, TestDelay { waited_ns: 0 });
+        let timing = crate::timing::Timing::new(300, 400, 10_000).unwrap();
+        sensor.set_timing(timing).unwrap();
+        assert_eq!(timing, sensor.timing());
+        assert_eq!(Ok(10), block_on(sensor.read()));
+        let (_, _, _, delay) = sensor.split();
+        assert_eq!(400_000, delay.waited_ns);
+    }
+
+    #[test]
+    fn read_returns_error_when_led_fails() {
+        let led_pin = TestOutputPin { fail: true, set_high_calls: 0 };
+        let mut sensor = super::Gp2y1014au::new(led_pin, TestAnalogPin, TestAdc, TestDelay { waited_ns: 0 });
+        assert_eq!(Err(crate::sensor::Error::LedError(ErrorKind::Other)), block_on(sensor.read()));
+        let (led_pin, _, _, _) = sensor.split();
+        assert_eq!(1, led_pin.set_high_calls);
+    }
+
+    #[test]
+    fn read_turns_the_led_off_right_away_when_the_adc_fails() {
+        let led_pin = TestOutputPin { fail: false, set_high_calls: 0 };
+        let mut sensor = super::Gp2y1014au::new(led_pin, TestAnalogPin, TestFailingAdc, TestDelay { waited_ns: 0 });
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), block_on(sensor.read()));
+        let (led_pin, _, _, delay) = sensor.split();
+        assert_eq!(1, led_pin.set_high_calls);
+        assert_eq!(280_000, delay.waited_ns);
+    }
+
+    #[test]
+    fn read_waits_for_the_rest_of_the_cycle_between_pulses() {
+        let led_pin = TestOutputPin { fail: false, set_high_calls: 0 };
+        let mut sensor = super::Gp2y1014au::new(led_pin, TestAnalogPin, TestAdc, TestDelay { waited_ns: 0 });
+        assert_eq!(Ok(10), block_on(sensor.read()));
+        assert_eq!(Ok(10), block_on(sensor.read()));
+        sensor.reset();
+        assert_eq!(Ok(10), block_on(sensor.read()));
+        let (_, _, _, delay) = sensor.split();
+        assert_eq!(10_320_000 + 320_000, delay.waited_ns);
+    }
+}
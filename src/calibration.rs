@@ -0,0 +1,207 @@
+use crate::sensor::ConfigError;
+use crate::units::{MicrogramsPerM3, Millivolts};
+
+/// Conversion parameters from the sensor output voltage to dust density.
+///
+/// The dust density is computed as `(voltage - v_oc_mv) / sensitivity_mv_per_ug`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    /// The output voltage when no dust is present.
+    pub v_oc_mv: Millivolts,
+    /// The output voltage increase in millivolts per µg/m³.
+    pub sensitivity_mv_per_ug: f32,
+}
+
+impl Calibration {
+    /// Typical output voltage when no dust is present.
+    pub const V_OC_MV: Millivolts = Millivolts(600.0);
+
+    /// Typical sensitivity in millivolts per µg/m³, which is 0.5V per 100µg/m³.
+    pub const SENSITIVITY_MV_PER_UG: f32 = 5.0;
+
+    /// Returns the typical calibration from the datasheet.
+    pub const fn datasheet() -> Self {
+        Self {
+            v_oc_mv: Self::V_OC_MV,
+            sensitivity_mv_per_ug: Self::SENSITIVITY_MV_PER_UG,
+        }
+    }
+
+    /// Creates a calibration from the no-dust voltage and the sensitivity in mV per µg/m³.
+    ///
+    /// Returns `ConfigError::CalibrationOutOfRange` if the calibration is rejected by `validate`.
+    pub fn new(v_oc_mv: Millivolts, sensitivity_mv_per_ug: f32) -> Result<Self, ConfigError> {
+        let calibration = Self {
+            v_oc_mv,
+            sensitivity_mv_per_ug,
+        };
+        calibration.validate()?;
+        Ok(calibration)
+    }
+
+    /// Checks that the sensitivity is positive and the no-dust voltage is not negative.
+    ///
+    /// Returns `ConfigError::CalibrationOutOfRange` otherwise. The no-dust voltage is checked against the
+    /// reference voltage of the ADC by `Gp2y1014au::set_calibration`.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.sensitivity_mv_per_ug.is_nan() || self.sensitivity_mv_per_ug <= 0.0 || self.v_oc_mv.0.is_nan() || self.v_oc_mv.0 < 0.0 {
+            return Err(ConfigError::CalibrationOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// Converts an output voltage to a dust density.
+    ///
+    /// Voltages below the no-dust voltage are clamped to 0.
+    pub fn density(&self, voltage: Millivolts) -> MicrogramsPerM3 {
+        let density = (voltage - self.v_oc_mv).0 / self.sensitivity_mv_per_ug;
+        MicrogramsPerM3(if density > 0.0 { density } else { 0.0 })
+    }
+
+    /// Converts an output voltage in millivolts to a dust density in µg/m³ using `f64` math.
+    ///
+    /// The calibration itself stays `f32`, its values are widened before the conversion. The unit newtypes
+    /// are `f32`, so this takes and returns bare numbers.
+    pub fn density_f64(&self, voltage_mv: f64) -> f64 {
+        let density = (voltage_mv - self.v_oc_mv.0 as f64) / self.sensitivity_mv_per_ug as f64;
+        if density > 0.0 { density } else { 0.0 }
+    }
+}
+
+/// Defaults to the typical calibration from the datasheet, see `Calibration::datasheet`.
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::datasheet()
+    }
+}
+
+/// Conversion from the sensor output voltage to dust density by interpolating between measured points.
+///
+/// This fits sensors which are not linear enough for a single `Calibration`, for example near clean air.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalibrationCurve<const N: usize> {
+    points: [(Millivolts, MicrogramsPerM3); N],
+}
+
+impl<const N: usize> CalibrationCurve<N> {
+    /// Creates a curve from `(voltage, density)` points.
+    ///
+    /// Returns `ConfigError::PointsNotIncreasing` unless the voltages are strictly increasing.
+    pub fn new(points: [(Millivolts, MicrogramsPerM3); N]) -> Result<Self, ConfigError> {
+        const { assert!(N >= 2, "CalibrationCurve needs at least two points") };
+        if points.windows(2).any(|pair| pair[0].0 >= pair[1].0 || f32::from(pair[0].0).is_nan() || f32::from(pair[1].0).is_nan()) {
+            return Err(ConfigError::PointsNotIncreasing);
+        }
+        Ok(Self { points })
+    }
+
+    /// Returns the points of the curve.
+    pub fn points(&self) -> &[(Millivolts, MicrogramsPerM3); N] {
+        &self.points
+    }
+
+    /// Converts an output voltage to a dust density.
+    ///
+    /// The density is interpolated linearly between the surrounding points, voltages outside of the curve
+    /// are extrapolated using the slope of the first or last segment. Negative densities are clamped to 0.
+    pub fn density(&self, voltage: Millivolts) -> MicrogramsPerM3 {
+        let segment = self.points.windows(2)
+            .position(|pair| voltage < pair[1].0)
+            .unwrap_or(N - 2);
+        let (v0, d0) = self.points[segment];
+        let (v1, d1) = self.points[segment + 1];
+        let density = d0.0 + (voltage - v0).0 * (d1 - d0).0 / (v1 - v0).0;
+        MicrogramsPerM3(if density > 0.0 { density } else { 0.0 })
+    }
+}
+
+/// Transfer function from the sensor output voltage to dust density.
+///
+/// It is implemented by `Calibration`, the linear model used by default, by `CalibrationCurve`, and
+/// for closures taking `Millivolts` and returning `MicrogramsPerM3`, so any fit can be used with
+/// `Gp2y1014au::read_dust_density_with`.
+pub trait Converter {
+    /// Converts an output voltage to a dust density.
+    fn convert(&self, voltage: Millivolts) -> MicrogramsPerM3;
+}
+
+impl Converter for Calibration {
+    fn convert(&self, voltage: Millivolts) -> MicrogramsPerM3 {
+        self.density(voltage)
+    }
+}
+
+impl<const N: usize> Converter for CalibrationCurve<N> {
+    fn convert(&self, voltage: Millivolts) -> MicrogramsPerM3 {
+        self.density(voltage)
+    }
+}
+
+impl<F> Converter for F
+where
+    F: Fn(Millivolts) -> MicrogramsPerM3,
+{
+    fn convert(&self, voltage: Millivolts) -> MicrogramsPerM3 {
+        self(voltage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Calibration, CalibrationCurve, Converter};
+    use crate::sensor::ConfigError;
+    use crate::units::{MicrogramsPerM3, Millivolts};
+    
+    #[test]
+    fn new_accepts_the_datasheet_calibration() {
+        assert_eq!(Ok(Calibration::datasheet()), Calibration::new(Millivolts(600.0), 5.0));
+    }
+
+    #[test]
+    fn new_rejects_a_non_positive_sensitivity() {
+        assert_eq!(Err(ConfigError::CalibrationOutOfRange), Calibration::new(Millivolts(600.0), 0.0));
+        assert_eq!(Err(ConfigError::CalibrationOutOfRange), Calibration::new(Millivolts(600.0), -5.0));
+    }
+
+    #[test]
+    fn density_is_linear_above_the_no_dust_voltage() {
+        let calibration = Calibration { v_oc_mv: Millivolts(600.0), ..Default::default() };
+        assert_eq!(MicrogramsPerM3(100.0), calibration.density(Millivolts(1100.0)));
+        assert_eq!(MicrogramsPerM3(0.0), calibration.density(Millivolts(500.0)));
+    }
+
+    #[test]
+    fn density_f64_matches_density() {
+        let calibration = Calibration::datasheet();
+        assert_eq!(80.0, calibration.density_f64(1000.0));
+        assert_eq!(0.0, calibration.density_f64(500.0));
+    }
+
+    #[test]
+    fn curve_interpolates_between_points() {
+        let curve = CalibrationCurve::new([(Millivolts(600.0), MicrogramsPerM3(0.0)), (Millivolts(800.0), MicrogramsPerM3(20.0)), (Millivolts(1200.0), MicrogramsPerM3(120.0))]).unwrap();
+        assert_eq!(MicrogramsPerM3(10.0), curve.density(Millivolts(700.0)));
+        assert_eq!(MicrogramsPerM3(20.0), curve.density(Millivolts(800.0)));
+        assert_eq!(MicrogramsPerM3(70.0), curve.density(Millivolts(1000.0)));
+    }
+
+    #[test]
+    fn curve_extrapolates_with_the_end_segments() {
+        let curve = CalibrationCurve::new([(Millivolts(600.0), MicrogramsPerM3(0.0)), (Millivolts(800.0), MicrogramsPerM3(20.0)), (Millivolts(1200.0), MicrogramsPerM3(120.0))]).unwrap();
+        assert_eq!(MicrogramsPerM3(145.0), curve.density(Millivolts(1300.0)));
+        assert_eq!(MicrogramsPerM3(0.0), curve.density(Millivolts(500.0)));
+    }
+
+    #[test]
+    fn curve_rejects_points_not_strictly_increasing() {
+        assert_eq!(Err(ConfigError::PointsNotIncreasing), CalibrationCurve::new([(Millivolts(600.0), MicrogramsPerM3(0.0)), (Millivolts(600.0), MicrogramsPerM3(20.0))]));
+        assert_eq!(Err(ConfigError::PointsNotIncreasing), CalibrationCurve::new([(Millivolts(800.0), MicrogramsPerM3(0.0)), (Millivolts(600.0), MicrogramsPerM3(20.0))]));
+    }
+
+    #[test]
+    fn closures_are_converters() {
+        let quadratic = |voltage: Millivolts| MicrogramsPerM3(voltage.0 * voltage.0 / 1000.0);
+        assert_eq!(MicrogramsPerM3(1000.0), quadratic.convert(Millivolts(1000.0)));
+        assert_eq!(Calibration::datasheet().density(Millivolts(1100.0)), Calibration::datasheet().convert(Millivolts(1100.0)));
+    }
+}
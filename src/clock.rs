@@ -0,0 +1,17 @@
+/// Monotonic time source used to timestamp readings.
+///
+/// The unit is up to the implementation, for example milliseconds or timer ticks. It is implemented
+/// for closures returning `u64`, so a free-running timer can be wrapped without a new type.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> u64;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> u64,
+{
+    fn now(&self) -> u64 {
+        self()
+    }
+}
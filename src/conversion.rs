@@ -0,0 +1,149 @@
+//! Conversions from dust density to other air quality measures.
+//!
+//! These are free functions so they can also be used on densities that did not come from a sensor instance.
+
+use crate::units::MicrogramsPerM3;
+
+/// Unit of a dust density.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DensityUnit {
+    /// Micrograms per cubic meter, the unit used throughout this crate.
+    #[default]
+    MicrogramsPerM3,
+    /// Milligrams per cubic meter, as used by the Sharp datasheet.
+    MilligramsPerM3,
+}
+
+impl DensityUnit {
+    /// Converts a density to a bare number in this unit.
+    pub fn from_ug_m3(self, density: MicrogramsPerM3) -> f32 {
+        let ug_m3 = density.0;
+        match self {
+            DensityUnit::MicrogramsPerM3 => ug_m3,
+            DensityUnit::MilligramsPerM3 => ug_m3 / 1000.0,
+        }
+    }
+}
+
+/// EPA PM2.5 breakpoints as `(density_low, density_high, aqi_low, aqi_high)`.
+///
+/// These are the 24 hour breakpoints from the 2024 revision of the standard.
+const AQI_BREAKPOINTS: [(f32, f32, u16, u16); 6] = [
+    (0.0, 9.0, 0, 50),
+    (9.1, 35.4, 51, 100),
+    (35.5, 55.4, 101, 150),
+    (55.5, 125.4, 151, 200),
+    (125.5, 225.4, 201, 300),
+    (225.5, 325.4, 301, 500),
+];
+
+/// Converts a PM2.5 dust density to the US EPA air quality index.
+///
+/// The density is truncated to 0.1µg/m³ and interpolated linearly within its breakpoint range,
+/// as specified by the EPA. Densities above the top breakpoint saturate at 500, and zero,
+/// negative or NaN densities map to 0.
+pub fn aqi_from_density(density: MicrogramsPerM3) -> u16 {
+    let ug_m3 = density.0;
+    if ug_m3.is_nan() || ug_m3 <= 0.0 {
+        return 0;
+    }
+    let density = (ug_m3 * 10.0) as u32 as f32 / 10.0;
+    for &(density_low, density_high, aqi_low, aqi_high) in AQI_BREAKPOINTS.iter() {
+        if density <= density_high {
+            let aqi = (aqi_high - aqi_low) as f32 / (density_high - density_low) * (density - density_low) + aqi_low as f32;
+            return (aqi + 0.5) as u16;
+        }
+    }
+    500
+}
+
+/// Density at which `quality_score` reaches 0, the lower end of the EPA PM2.5 "Unhealthy" range.
+pub const QUALITY_SCORE_ZERO_UG_M3: MicrogramsPerM3 = MicrogramsPerM3(55.5);
+
+/// Maps a dust density to a score from 100 for clean air to 0 for unhealthy air.
+///
+/// The score falls linearly from 100 at 0µg/m³ to 0 at `QUALITY_SCORE_ZERO_UG_M3` and is rounded to the
+/// nearest integer. Higher densities stay at 0, and negative or NaN densities map to 100.
+pub fn quality_score(density: MicrogramsPerM3) -> u8 {
+    let density_ug_m3 = density.0;
+    if density_ug_m3.is_nan() || density_ug_m3 <= 0.0 {
+        return 100;
+    }
+    let score = 100.0 * (1.0 - density_ug_m3 / QUALITY_SCORE_ZERO_UG_M3.0);
+    if score > 0.0 { (score + 0.5) as u8 } else { 0 }
+}
+
+/// Assumed mass of a single PM2.5 particle in µg.
+///
+/// Particles are modeled as spheres with a radius of 0.44µm and a density of 1.65g/cm³, the assumptions
+/// commonly used to relate particle counts of consumer monitors to dust density.
+const PARTICLE_MASS_UG: f32 = 4.0 / 3.0 * core::f32::consts::PI * 0.44e-6 * 0.44e-6 * 0.44e-6 * 1.65e12;
+
+/// Number of 0.01 cubic foot volumes in a cubic meter.
+const VOLUMES_PER_M3: f32 = 3531.47;
+
+/// Estimates the particle count in particles per 0.01 cubic foot (PCS/0.01cf) from a dust density.
+///
+/// This uses the empirical relationship commonly cited for the GP2Y1014AU, which assumes every particle
+/// has the mass of a typical PM2.5 particle. The result is only an estimate as the sensor cannot count
+/// particles. Zero, negative or NaN densities map to 0.
+pub fn particles_per_0_01cf(density: MicrogramsPerM3) -> f32 {
+    let density_ug_m3 = density.0;
+    if density_ug_m3.is_nan() || density_ug_m3 <= 0.0 {
+        return 0.0;
+    }
+    density_ug_m3 / (PARTICLE_MASS_UG * VOLUMES_PER_M3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aqi_from_density, particles_per_0_01cf, quality_score, DensityUnit};
+    use crate::units::MicrogramsPerM3;
+
+    #[test]
+    fn density_unit_converts_from_micrograms() {
+        assert_eq!(250.0, DensityUnit::default().from_ug_m3(MicrogramsPerM3(250.0)));
+        assert_eq!(0.25, DensityUnit::MilligramsPerM3.from_ug_m3(MicrogramsPerM3(250.0)));
+    }
+
+    #[test]
+    fn aqi_from_density_matches_the_breakpoints() {
+        assert_eq!(0, aqi_from_density(MicrogramsPerM3(0.0)));
+        assert_eq!(50, aqi_from_density(MicrogramsPerM3(9.0)));
+        assert_eq!(51, aqi_from_density(MicrogramsPerM3(9.1)));
+        assert_eq!(100, aqi_from_density(MicrogramsPerM3(35.4)));
+        assert_eq!(151, aqi_from_density(MicrogramsPerM3(55.5)));
+        assert_eq!(500, aqi_from_density(MicrogramsPerM3(325.4)));
+    }
+
+    #[test]
+    fn aqi_from_density_interpolates_within_a_range() {
+        assert_eq!(25, aqi_from_density(MicrogramsPerM3(4.5)));
+        assert_eq!(51, aqi_from_density(MicrogramsPerM3(9.15)));
+    }
+
+    #[test]
+    fn aqi_from_density_saturates_out_of_range_densities() {
+        assert_eq!(500, aqi_from_density(MicrogramsPerM3(1000.0)));
+        assert_eq!(0, aqi_from_density(MicrogramsPerM3(-5.0)));
+        assert_eq!(0, aqi_from_density(MicrogramsPerM3(f32::NAN)));
+    }
+
+    #[test]
+    fn quality_score_falls_to_zero_at_unhealthy_air() {
+        assert_eq!(100, quality_score(MicrogramsPerM3(0.0)));
+        assert_eq!(50, quality_score(MicrogramsPerM3(27.75)));
+        assert_eq!(0, quality_score(MicrogramsPerM3(55.5)));
+        assert_eq!(0, quality_score(MicrogramsPerM3(300.0)));
+        assert_eq!(100, quality_score(MicrogramsPerM3(f32::NAN)));
+    }
+
+    #[test]
+    fn particles_per_0_01cf_scales_the_density() {
+        let particles = particles_per_0_01cf(MicrogramsPerM3(100.0));
+        assert!((particles - 48_096.0).abs() < 10.0, "unexpected particle count {}", particles);
+        assert_eq!(0.0, particles_per_0_01cf(MicrogramsPerM3(-5.0)));
+        assert_eq!(0.0, particles_per_0_01cf(MicrogramsPerM3(f32::NAN)));
+    }
+}
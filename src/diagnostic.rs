@@ -0,0 +1,33 @@
+use crate::units::Millivolts;
+
+/// Outcome of `Gp2y1014au::self_test`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiagnosticStatus {
+    /// The output is low with the LED off and rises with the LED on.
+    Passed,
+    /// The output with the LED off is not below the no-dust voltage, so the data pin may be floating
+    /// or connected to the wrong signal.
+    DarkTooHigh,
+    /// The output does not rise with the LED on, so the LED or data pin is likely swapped or disconnected.
+    NoLedResponse,
+}
+
+/// Readings taken by `Gp2y1014au::self_test` and the conclusion drawn from them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DiagnosticResult {
+    /// The output voltage with the LED off.
+    pub dark_mv: Millivolts,
+    /// The output voltage with the LED on.
+    pub lit_mv: Millivolts,
+    /// The conclusion drawn from the readings.
+    pub status: DiagnosticStatus,
+}
+
+impl DiagnosticResult {
+    /// Returns true if the self-test passed.
+    pub fn passed(&self) -> bool {
+        self.status == DiagnosticStatus::Passed
+    }
+}
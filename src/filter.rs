@@ -0,0 +1,614 @@
+use crate::sensor::ConfigError;
+
+/// Moving average over the last `N` samples.
+///
+/// The samples are kept in a fixed-size ring buffer, so no allocation is needed.
+/// Until `N` samples were pushed the average is taken over the samples pushed so far.
+#[derive(Clone, Debug)]
+pub struct MovingAverage<const N: usize> {
+    samples: [f32; N],
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Creates an empty moving average.
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "MovingAverage needs room for at least one sample") };
+        Self {
+            samples: [0.0; N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Adds a sample to the window, replacing the oldest one once the window is full,
+    /// and returns the new average.
+    pub fn push(&mut self, sample: f32) -> f32 {
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        if self.len < N {
+            self.len += 1;
+        }
+        self.value().unwrap_or(sample)
+    }
+
+    /// Returns the average of the samples in the window, or `None` if no sample was pushed yet.
+    pub fn value(&self) -> Option<f32> {
+        if self.len == 0 {
+            return None;
+        }
+        let sum: f32 = self.samples[..self.len].iter().sum();
+        Some(sum / self.len as f32)
+    }
+
+    /// Returns the number of samples in the window.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no sample was pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Empties the window, so the average restarts from the next sample.
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Weighted moving average over the last `N` samples, with triangular weights favoring recent samples.
+///
+/// The newest sample has the weight `len`, the one before `len - 1`, down to 1 for the oldest one, where `len`
+/// is the number of samples in the window. This follows step changes faster than `MovingAverage` while
+/// still forgetting old samples completely, unlike `ExponentialMovingAverage`.
+#[derive(Clone, Debug)]
+pub struct WeightedWindow<const N: usize> {
+    samples: [f32; N],
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> WeightedWindow<N> {
+    /// Creates an empty window.
+    pub const fn new() -> Self {
+        const { assert!(N > 0, "WeightedWindow needs room for at least one sample") };
+        Self {
+            samples: [0.0; N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Adds a sample to the window, replacing the oldest one once the window is full,
+    /// and returns the new weighted average.
+    pub fn push(&mut self, sample: f32) -> f32 {
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        if self.len < N {
+            self.len += 1;
+        }
+        self.value().unwrap_or(sample)
+    }
+
+    /// Returns the weighted average of the samples in the window, or `None` if no sample was pushed yet.
+    pub fn value(&self) -> Option<f32> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut sum = 0.0;
+        for age in 0..self.len {
+            let index = (self.next + N - 1 - age) % N;
+            sum += self.samples[index] * (self.len - age) as f32;
+        }
+        let total_weight = (self.len * (self.len + 1) / 2) as f32;
+        Some(sum / total_weight)
+    }
+
+    /// Returns the number of samples in the window.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no sample was pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Empties the window, so the average restarts from the next sample.
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for WeightedWindow<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Exponential moving average, keeping a single value as state.
+///
+/// Each sample updates the value as `alpha * sample + (1 - alpha) * value`. The first sample
+/// seeds the value directly rather than blending it with 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExponentialMovingAverage {
+    alpha: f32,
+    value: Option<f32>,
+}
+
+impl ExponentialMovingAverage {
+    /// Creates an empty exponential moving average with the smoothing factor `alpha`.
+    ///
+    /// A higher `alpha` follows the samples more closely. Returns `ConfigError::AlphaOutOfRange`
+    /// if `alpha` is not within `0.0..=1.0`.
+    pub fn new(alpha: f32) -> Result<Self, ConfigError> {
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(ConfigError::AlphaOutOfRange);
+        }
+        Ok(Self { alpha, value: None })
+    }
+
+    /// Returns the smoothing factor.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Updates the average with a sample and returns the new average.
+    pub fn push(&mut self, sample: f32) -> f32 {
+        let value = match self.value {
+            Some(value) => self.alpha * sample + (1.0 - self.alpha) * value,
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+
+    /// Returns the average, or `None` if no sample was pushed yet.
+    pub fn value(&self) -> Option<f32> {
+        self.value
+    }
+
+    /// Clears the average while keeping `alpha`, so the next sample seeds it again.
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// First-order low-pass filter, the digital equivalent of an RC filter.
+///
+/// The smoothing coefficient is derived from the cutoff frequency and the rate at which samples are filtered.
+/// The first sample seeds the output directly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IirLowPass {
+    alpha: f32,
+    value: Option<f32>,
+}
+
+impl IirLowPass {
+    /// Creates an empty filter with the cutoff frequency `cutoff_hz` for samples taken at `sample_rate_hz`.
+    ///
+    /// Returns `ConfigError::CutoffOutOfRange` unless `cutoff_hz` is positive and below the Nyquist
+    /// frequency, half of `sample_rate_hz`.
+    pub fn new(cutoff_hz: f32, sample_rate_hz: f32) -> Result<Self, ConfigError> {
+        if cutoff_hz.is_nan() || sample_rate_hz.is_nan() || cutoff_hz <= 0.0 || cutoff_hz >= sample_rate_hz / 2.0 {
+            return Err(ConfigError::CutoffOutOfRange);
+        }
+        let rc = 1.0 / (2.0 * core::f32::consts::PI * cutoff_hz);
+        let dt = 1.0 / sample_rate_hz;
+        Ok(Self { alpha: dt / (rc + dt), value: None })
+    }
+
+    /// Returns the smoothing coefficient derived from the frequencies.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Filters a sample and returns the new output.
+    pub fn filter(&mut self, sample: f32) -> f32 {
+        let value = match self.value {
+            Some(value) => value + self.alpha * (sample - value),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+
+    /// Returns the output, or `None` if no sample was filtered yet.
+    pub fn value(&self) -> Option<f32> {
+        self.value
+    }
+
+    /// Clears the output while keeping `alpha`, so the next sample seeds it again.
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// Outlier rejection over a window of the last `N` samples, replacing spikes by the window median.
+///
+/// A sample is a spike if it is more than `k` median absolute deviations (MADs) away from the median of
+/// the previous `N` samples. The samples themselves always enter the window, so a lasting change of the
+/// signal passes through once it makes up half of the window. Until `N` samples were filtered they are
+/// passed through unmodified.
+#[derive(Clone, Debug)]
+pub struct HampelFilter<const N: usize> {
+    k: f32,
+    samples: [f32; N],
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> HampelFilter<N> {
+    /// Creates an empty filter rejecting samples more than `k` MADs from the median.
+    ///
+    /// Returns `ConfigError::FactorOutOfRange` if `k` is negative or NaN.
+    pub fn new(k: f32) -> Result<Self, ConfigError> {
+        if k.is_nan() || k < 0.0 {
+            return Err(ConfigError::FactorOutOfRange);
+        }
+        const { assert!(N > 0, "HampelFilter needs room for at least one sample") };
+        Ok(Self { k, samples: [0.0; N], next: 0, len: 0 })
+    }
+
+    /// Returns the number of MADs from the median above which samples are replaced.
+    pub fn k(&self) -> f32 {
+        self.k
+    }
+
+    /// Filters a sample, returning either the sample or the window median if it is a spike.
+    pub fn filter(&mut self, sample: f32) -> f32 {
+        let output = if self.len < N {
+            self.len += 1;
+            sample
+        } else {
+            let mut sorted = self.samples;
+            let median = sorted_median(&mut sorted);
+            for value in sorted.iter_mut() {
+                *value = (*value - median).abs();
+            }
+            let mad = sorted_median(&mut sorted);
+            if (sample - median).abs() > self.k * mad { median } else { sample }
+        };
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        output
+    }
+
+    /// Empties the window while keeping `k`, so the next `N` samples are passed through again.
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+/// Sorts `values` and returns their median, averaging the two middle values for an even count.
+fn sorted_median<const N: usize>(values: &mut [f32; N]) -> f32 {
+    values.sort_unstable_by(|a, b| a.total_cmp(b));
+    if N % 2 == 1 {
+        values[N / 2]
+    } else {
+        (values[N / 2 - 1] + values[N / 2]) / 2.0
+    }
+}
+
+/// One-dimensional Kalman filter for a slowly changing signal.
+///
+/// `q` is the process noise, how much the true value is expected to drift between samples, and `r` is
+/// the measurement noise, the variance of the samples around the true value. The first sample seeds the
+/// estimate directly with an error variance of `r`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kalman1D {
+    q: f32,
+    r: f32,
+    x: Option<f32>,
+    p: f32,
+}
+
+impl Kalman1D {
+    /// Creates an empty filter with the process noise `q` and the measurement noise `r`.
+    ///
+    /// Returns `ConfigError::NoiseOutOfRange` if `q` is negative or `r` is not positive.
+    pub fn new(q: f32, r: f32) -> Result<Self, ConfigError> {
+        if q.is_nan() || r.is_nan() || q < 0.0 || r <= 0.0 {
+            return Err(ConfigError::NoiseOutOfRange);
+        }
+        Ok(Self { q, r, x: None, p: r })
+    }
+
+    /// Returns the process noise.
+    pub fn q(&self) -> f32 {
+        self.q
+    }
+
+    /// Returns the measurement noise.
+    pub fn r(&self) -> f32 {
+        self.r
+    }
+
+    /// Updates the estimate with a measurement and returns the new estimate.
+    pub fn update(&mut self, measurement: f32) -> f32 {
+        let x = match self.x {
+            Some(x) => {
+                let p = self.p + self.q;
+                let gain = p / (p + self.r);
+                self.p = (1.0 - gain) * p;
+                x + gain * (measurement - x)
+            }
+            None => measurement,
+        };
+        self.x = Some(x);
+        x
+    }
+
+    /// Returns the estimate, or `None` if no measurement was fed yet.
+    pub fn value(&self) -> Option<f32> {
+        self.x
+    }
+
+    /// Clears the estimate while keeping `q` and `r`, so the next measurement seeds it again.
+    pub fn reset(&mut self) {
+        self.x = None;
+        self.p = self.r;
+    }
+}
+
+/// Holds a value until it moved more than `step` away from the last reported value.
+///
+/// This is not a filter, the noise is not reduced, but it keeps a display from flickering between
+/// neighbouring values while the signal is steady. The first value is always reported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Deadband {
+    step: f32,
+    last: Option<f32>,
+}
+
+impl Deadband {
+    /// Creates a deadband which reports values moving more than `step` from the last reported one.
+    ///
+    /// Returns `ConfigError::FactorOutOfRange` if `step` is negative or NaN.
+    pub fn new(step: f32) -> Result<Self, ConfigError> {
+        if step.is_nan() || step < 0.0 {
+            return Err(ConfigError::FactorOutOfRange);
+        }
+        Ok(Self { step, last: None })
+    }
+
+    /// Returns the step a value has to move before it is reported.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Returns `value` if it moved more than `step` from the last reported value, or the last reported value otherwise.
+    pub fn apply(&mut self, value: f32) -> f32 {
+        match self.last {
+            Some(last) if (value - last).abs() <= self.step => last,
+            _ => {
+                self.last = Some(value);
+                value
+            }
+        }
+    }
+
+    /// Returns the last reported value, or `None` if no value was applied yet.
+    pub fn value(&self) -> Option<f32> {
+        self.last
+    }
+
+    /// Forgets the last reported value while keeping `step`, so the next value is reported.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Shape of the weights applied across a burst of samples, see `Gp2y1014au::read_windowed`.
+///
+/// The tapered shapes weight the middle of the burst most, so transients at its edges barely affect
+/// the result. For white noise this costs some averaging: with 16 samples the standard deviation of the
+/// weighted mean is about 1.19 times that of a flat average for `Hamming` and 1.24 times for `Gaussian`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WindowShape {
+    /// Equal weights, the same as a plain average.
+    Flat,
+    /// Hamming window, `0.54 - 0.46 * cos(2 * pi * n / (N - 1))`.
+    #[default]
+    Hamming,
+    /// Gaussian window centered on the middle sample, with a standard deviation of 0.4 times half the burst.
+    Gaussian,
+}
+
+impl WindowShape {
+    /// Returns the weight of sample `n` of a burst of `N` samples, the weights are not normalized.
+    ///
+    /// `Flat` weights are constant. The other shapes need `libm` functions, which cannot be evaluated
+    /// at compile time, so they are computed on each call.
+    pub fn weight<const N: usize>(self, n: usize) -> f32 {
+        if N < 2 {
+            return 1.0;
+        }
+        let span = (N - 1) as f32;
+        match self {
+            WindowShape::Flat => 1.0,
+            WindowShape::Hamming => 0.54 - 0.46 * libm::cosf(2.0 * core::f32::consts::PI * n as f32 / span),
+            WindowShape::Gaussian => {
+                let x = (n as f32 - span / 2.0) / (0.4 * span / 2.0);
+                libm::expf(-0.5 * x * x)
+            }
+        }
+    }
+
+    /// Returns the weighted mean of `samples` using this shape.
+    pub fn mean<const N: usize>(self, samples: &[f32; N]) -> f32 {
+        const { assert!(N > 0, "WindowShape::mean needs at least one sample") };
+        let mut sum = 0.0;
+        let mut total_weight = 0.0;
+        for (n, &sample) in samples.iter().enumerate() {
+            let weight = self.weight::<N>(n);
+            sum += weight * sample;
+            total_weight += weight;
+        }
+        sum / total_weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deadband, ExponentialMovingAverage, HampelFilter, IirLowPass, Kalman1D, MovingAverage, WeightedWindow, WindowShape};
+    use crate::sensor::ConfigError;
+
+    #[test]
+    fn moving_average_averages_the_samples_pushed_so_far() {
+        let mut average: MovingAverage<4> = MovingAverage::new();
+        assert_eq!(None, average.value());
+        assert_eq!(2.0, average.push(2.0));
+        assert_eq!(3.0, average.push(4.0));
+    }
+
+    #[test]
+    fn moving_average_drops_the_oldest_sample() {
+        let mut average: MovingAverage<2> = MovingAverage::new();
+        average.push(10.0);
+        average.push(2.0);
+        assert_eq!(3.0, average.push(4.0));
+        assert_eq!(2, average.len());
+    }
+
+    #[test]
+    fn weighted_window_favors_recent_samples() {
+        let mut window: WeightedWindow<3> = WeightedWindow::new();
+        assert_eq!(None, window.value());
+        assert_eq!(6.0, window.push(6.0));
+        assert_eq!(10.0, window.push(12.0));
+        window.push(0.0);
+        assert_eq!(2.0, window.push(0.0));
+        assert_eq!(3, window.len());
+    }
+
+    #[test]
+    fn exponential_moving_average_is_seeded_by_the_first_sample() {
+        let mut average = ExponentialMovingAverage::new(0.5).unwrap();
+        assert_eq!(8.0, average.push(8.0));
+        assert_eq!(6.0, average.push(4.0));
+    }
+
+    #[test]
+    fn exponential_moving_average_rejects_alpha_out_of_range() {
+        assert_eq!(Err(ConfigError::AlphaOutOfRange), ExponentialMovingAverage::new(1.5));
+        assert_eq!(Err(ConfigError::AlphaOutOfRange), ExponentialMovingAverage::new(f32::NAN));
+    }
+
+    #[test]
+    fn low_pass_coefficient_follows_the_cutoff() {
+        let mut low_pass = IirLowPass::new(1.0, 100.0).unwrap();
+        let rc = 1.0 / (2.0 * core::f32::consts::PI);
+        assert_eq!(0.01 / (rc + 0.01), low_pass.alpha());
+        assert_eq!(10.0, low_pass.filter(10.0));
+        assert_eq!(10.0 + low_pass.alpha() * 10.0, low_pass.filter(20.0));
+    }
+
+    #[test]
+    fn low_pass_rejects_cutoffs_above_nyquist() {
+        assert_eq!(Err(ConfigError::CutoffOutOfRange), IirLowPass::new(50.0, 100.0));
+        assert_eq!(Err(ConfigError::CutoffOutOfRange), IirLowPass::new(0.0, 100.0));
+        assert!(IirLowPass::new(49.0, 100.0).is_ok());
+    }
+
+    #[test]
+    fn hampel_passes_samples_until_the_window_is_full() {
+        let mut hampel: HampelFilter<3> = HampelFilter::new(3.0).unwrap();
+        assert_eq!(10.0, hampel.filter(10.0));
+        assert_eq!(500.0, hampel.filter(500.0));
+        assert_eq!(11.0, hampel.filter(11.0));
+    }
+
+    #[test]
+    fn hampel_replaces_spikes_by_the_median() {
+        let mut hampel: HampelFilter<5> = HampelFilter::new(3.0).unwrap();
+        for sample in [10.0, 12.0, 11.0, 9.0, 10.0] {
+            hampel.filter(sample);
+        }
+        assert_eq!(10.0, hampel.filter(100.0));
+        assert_eq!(11.0, hampel.filter(100.0));
+        assert_eq!(12.0, hampel.filter(12.0));
+    }
+
+    #[test]
+    fn hampel_rejects_negative_factors() {
+        assert_eq!(Err(ConfigError::FactorOutOfRange), HampelFilter::<3>::new(-1.0).map(|hampel| hampel.k()));
+    }
+
+    #[test]
+    fn kalman_is_seeded_by_the_first_measurement() {
+        let mut kalman = Kalman1D::new(0.0, 1.0).unwrap();
+        assert_eq!(None, kalman.value());
+        assert_eq!(10.0, kalman.update(10.0));
+        assert_eq!(15.0, kalman.update(20.0));
+        assert_eq!(Some(15.0), kalman.value());
+    }
+
+    #[test]
+    fn reset_clears_the_history_but_keeps_the_configuration() {
+        let mut average: MovingAverage<2> = MovingAverage::new();
+        average.push(10.0);
+        average.reset();
+        assert_eq!(20.0, average.push(20.0));
+        let mut ema = ExponentialMovingAverage::new(0.5).unwrap();
+        ema.push(10.0);
+        ema.reset();
+        assert_eq!((0.5, 20.0), (ema.alpha(), ema.push(20.0)));
+        let mut kalman = Kalman1D::new(0.1, 2.0).unwrap();
+        let fresh = kalman;
+        kalman.update(10.0);
+        kalman.update(12.0);
+        kalman.reset();
+        assert_eq!(fresh, kalman);
+    }
+
+    #[test]
+    fn kalman_rejects_invalid_noise() {
+        assert_eq!(Err(ConfigError::NoiseOutOfRange), Kalman1D::new(-1.0, 1.0));
+        assert_eq!(Err(ConfigError::NoiseOutOfRange), Kalman1D::new(0.1, 0.0));
+    }
+
+    #[test]
+    fn deadband_holds_small_moves() {
+        let mut deadband = Deadband::new(2.0).unwrap();
+        assert_eq!(10.0, deadband.apply(10.0));
+        assert_eq!(10.0, deadband.apply(11.5));
+        assert_eq!(10.0, deadband.apply(8.0));
+        assert_eq!(12.5, deadband.apply(12.5));
+        assert_eq!(Some(12.5), deadband.value());
+    }
+
+    #[test]
+    fn deadband_rejects_negative_steps() {
+        assert_eq!(Err(ConfigError::FactorOutOfRange), Deadband::new(-0.5));
+    }
+
+    #[test]
+    fn window_shapes_weight_the_middle_samples() {
+        assert_eq!(1.0, WindowShape::Flat.weight::<5>(0));
+        assert!((WindowShape::Hamming.weight::<5>(0) - 0.08).abs() < 1e-6);
+        assert!((WindowShape::Hamming.weight::<5>(2) - 1.0).abs() < 1e-6);
+        assert!((WindowShape::Gaussian.weight::<5>(2) - 1.0).abs() < 1e-6);
+        assert!(WindowShape::Gaussian.weight::<5>(0) < 0.05);
+    }
+
+    #[test]
+    fn window_shape_mean_suppresses_edge_samples() {
+        let samples = [100.0, 10.0, 10.0, 10.0, 100.0];
+        assert_eq!(46.0, WindowShape::Flat.mean(&samples));
+        let hamming = WindowShape::Hamming.mean(&samples);
+        assert!(hamming > 10.0 && hamming < 20.0, "unexpected mean {}", hamming);
+    }
+}
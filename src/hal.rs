@@ -0,0 +1,93 @@
+//! Trait bounds of the sensor peripherals, selected by the `eh02` and `eh1` features.
+//!
+//! With `eh02`, the default, the LED pin is an embedded-hal 0.2 `digital::v2::OutputPin`, the delay an embedded-hal 0.2
+//! `DelayUs<u32>` and `crate::adc` re-exports the embedded-hal 0.2 ADC traits, so the peripherals of a 0.2 HAL are used
+//! as they are. With `eh1` they are the embedded-hal 1.0 `OutputPin` and `DelayNs`, and the ADC traits of `crate::adc`.
+//!
+//! Cargo features are additive, so `eh1` takes precedence over `eh02` when both are enabled, and is also used when
+//! neither is. The `asynch` driver always uses embedded-hal 1.0 and embedded-hal-async.
+
+#[cfg(all(feature = "eh02", not(feature = "eh1")))]
+pub use embedded_hal_02::digital::v2::OutputPin;
+#[cfg(not(all(feature = "eh02", not(feature = "eh1"))))]
+pub use embedded_hal::digital::OutputPin;
+
+/// Microsecond delay used to time the LED pulse.
+///
+/// It is implemented for every delay of the selected embedded-hal version and does not have to be implemented by hand.
+pub trait DelayUs {
+    /// Pauses execution for at least `us` microseconds.
+    fn delay_us(&mut self, us: u32);
+}
+
+#[cfg(all(feature = "eh02", not(feature = "eh1")))]
+impl<D: embedded_hal_02::blocking::delay::DelayUs<u32>> DelayUs for D {
+    fn delay_us(&mut self, us: u32) {
+        embedded_hal_02::blocking::delay::DelayUs::delay_us(self, us);
+    }
+}
+
+#[cfg(not(all(feature = "eh02", not(feature = "eh1"))))]
+impl<D: embedded_hal::delay::DelayNs> DelayUs for D {
+    fn delay_us(&mut self, us: u32) {
+        embedded_hal::delay::DelayNs::delay_us(self, us);
+    }
+}
+
+#[cfg(all(test, feature = "eh02", not(feature = "eh1")))]
+mod tests {
+    use embedded_hal_02::adc::{Channel, OneShot};
+    use embedded_hal_02::blocking::delay::DelayUs;
+    use embedded_hal_02::digital::v2::OutputPin;
+
+    struct TestPin {
+        high: bool,
+    }
+    struct TestAdc;
+    struct TestAnalogPin;
+    struct TestDelay {
+        waited_us: u32,
+    }
+
+    impl OutputPin for TestPin {
+        type Error = ();
+        fn set_low(&mut self) -> Result<(), ()> {
+            self.high = false;
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), ()> {
+            self.high = true;
+            Ok(())
+        }
+    }
+
+    impl Channel<TestAdc> for TestAnalogPin {
+        type ID = u8;
+        fn channel() -> u8 {
+            1
+        }
+    }
+
+    impl OneShot<TestAdc, u16, TestAnalogPin> for TestAdc {
+        type Error = ();
+        fn read(&mut self, _: &mut TestAnalogPin) -> nb::Result<u16, ()> {
+            Ok(1100)
+        }
+    }
+
+    impl DelayUs<u32> for TestDelay {
+        fn delay_us(&mut self, us: u32) {
+            self.waited_us += us;
+        }
+    }
+
+    #[test]
+    fn sensor_uses_embedded_hal_02_peripherals() {
+        let pin = TestPin { high: false };
+        let mut sensor = crate::sensor::Gp2y1014au::new(pin, TestAnalogPin, TestAdc, TestDelay { waited_us: 0 });
+        assert_eq!(Ok(1100), sensor.read());
+        assert!(sensor.pin_led().high);
+        let (_, _, _, delay) = sensor.split();
+        assert_eq!(320, delay.waited_us);
+    }
+}
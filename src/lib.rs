@@ -1,3 +1,22 @@
 #![deny(unsafe_code)]
 #![no_std]
-pub mod sensor;
\ No newline at end of file
+pub mod adc;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod calibration;
+pub mod clock;
+pub mod conversion;
+pub mod diagnostic;
+pub mod filter;
+#[cfg(feature = "fixed-point")]
+pub mod fixed;
+pub mod hal;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod model;
+pub mod reading;
+pub mod sensor;
+pub mod stats;
+pub mod threshold;
+pub mod timing;
+pub mod units;
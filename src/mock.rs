@@ -0,0 +1,226 @@
+//! Test doubles for the peripherals of the sensor, to unit test code using a `Gp2y1014au` off target.
+//!
+//! The pin and the delay implement the embedded-hal 1.0 traits, and the embedded-hal 0.2 ones with the `eh02` feature.
+//!
+//! ```
+//! use gp2y1014au::mock::{MockAdc, MockChannel, MockDelay, MockPin};
+//! use gp2y1014au::sensor::Gp2y1014au;
+//!
+//! let adc = MockAdc::new(&[2048u16, 1024]);
+//! let mut sensor = Gp2y1014au::new(MockPin::new(), MockChannel, adc, MockDelay::new());
+//! assert_eq!(Ok(2048), sensor.read());
+//! assert_eq!(Ok(1024), sensor.read());
+//! assert!(sensor.pin_led().is_high());
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{ErrorKind, ErrorType, OutputPin};
+
+use crate::adc::{Channel, OneShot, PowerControl};
+
+/// Error returned by a failing `MockAdc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockError;
+
+/// Fake ADC returning a scripted sequence of words.
+///
+/// The words are returned in order and the sequence restarts once all of them were returned.
+pub struct MockAdc<'a, Word> {
+    values: &'a [Word],
+    index: usize,
+    failing: bool,
+    powered: bool,
+}
+
+impl<'a, Word: Copy> MockAdc<'a, Word> {
+    /// Creates an ADC returning `values` in order.
+    ///
+    /// # Panics
+    ///
+    /// Reading panics if `values` is empty.
+    pub fn new(values: &'a [Word]) -> Self {
+        Self { values, index: 0, failing: false, powered: true }
+    }
+
+    /// Makes every following conversion fail with `MockError` while `failing` is true, `set_failing(false)` restores them.
+    pub fn set_failing(&mut self, failing: bool) {
+        self.failing = failing;
+    }
+
+    /// Returns the number of successful conversions.
+    pub fn reads(&self) -> usize {
+        self.index
+    }
+
+    /// Returns false after `PowerControl::power_down` until powered up again.
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+}
+
+impl<Word> PowerControl for MockAdc<'_, Word> {
+    fn power_up(&mut self) {
+        self.powered = true;
+    }
+
+    fn power_down(&mut self) {
+        self.powered = false;
+    }
+}
+
+impl<'a, Word: Copy> OneShot<MockAdc<'a, Word>, Word, MockChannel> for MockAdc<'a, Word> {
+    type Error = MockError;
+
+    fn read(&mut self, _pin: &mut MockChannel) -> nb::Result<Word, Self::Error> {
+        if self.failing {
+            return Err(nb::Error::Other(MockError));
+        }
+        let value = self.values[self.index % self.values.len()];
+        self.index += 1;
+        Ok(value)
+    }
+}
+
+/// Fake analog pin read by `MockAdc`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MockChannel;
+
+impl<Word> Channel<MockAdc<'_, Word>> for MockChannel {
+    type ID = u8;
+
+    fn channel() -> Self::ID {
+        0
+    }
+}
+
+/// Fake output pin recording its state, which can be set to fail.
+#[derive(Debug, Default)]
+pub struct MockPin {
+    high: bool,
+    failing: bool,
+    set_high_calls: usize,
+    set_low_calls: usize,
+}
+
+impl MockPin {
+    /// Creates a low pin which succeeds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes every following call fail with `ErrorKind::Other` while `failing` is true, `set_failing(false)` restores them.
+    /// The state is not changed by failed calls.
+    pub fn set_failing(&mut self, failing: bool) {
+        self.failing = failing;
+    }
+
+    /// Returns true if the pin was last set high.
+    pub fn is_high(&self) -> bool {
+        self.high
+    }
+
+    /// Returns the number of calls to `set_high`, including failed ones.
+    pub fn set_high_calls(&self) -> usize {
+        self.set_high_calls
+    }
+
+    /// Returns the number of calls to `set_low`, including failed ones.
+    pub fn set_low_calls(&self) -> usize {
+        self.set_low_calls
+    }
+}
+
+impl ErrorType for MockPin {
+    type Error = ErrorKind;
+}
+
+impl OutputPin for MockPin {
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_high_calls += 1;
+        if self.failing {
+            return Err(ErrorKind::Other);
+        }
+        self.high = true;
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_low_calls += 1;
+        if self.failing {
+            return Err(ErrorKind::Other);
+        }
+        self.high = false;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh02")]
+impl embedded_hal_02::digital::v2::OutputPin for MockPin {
+    type Error = ErrorKind;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_high(self)
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_low(self)
+    }
+}
+
+/// Fake delay returning right away and recording the total time it was asked to wait.
+#[derive(Debug, Default)]
+pub struct MockDelay {
+    waited_ns: u64,
+}
+
+impl MockDelay {
+    /// Creates a delay which has not waited yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the total time waited in nanoseconds.
+    pub fn waited_ns(&self) -> u64 {
+        self.waited_ns
+    }
+}
+
+impl DelayNs for MockDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.waited_ns += ns as u64;
+    }
+}
+
+#[cfg(feature = "eh02")]
+impl embedded_hal_02::blocking::delay::DelayUs<u32> for MockDelay {
+    fn delay_us(&mut self, us: u32) {
+        DelayNs::delay_us(self, us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MockAdc, MockChannel, MockDelay, MockError, MockPin};
+    use crate::sensor::{Error, Gp2y1014au};
+    use embedded_hal::digital::ErrorKind;
+
+    #[test]
+    fn mocks_drive_the_sensor() {
+        let mut sensor = Gp2y1014au::new(MockPin::new(), MockChannel, MockAdc::new(&[1u16, 2]), MockDelay::new());
+        assert_eq!(Ok(1), sensor.read());
+        assert_eq!(Ok(2), sensor.read());
+        assert_eq!(2, sensor.reader().reads());
+        assert_eq!(2, sensor.pin_led().set_low_calls());
+        assert_eq!(640_000, sensor.delay().waited_ns());
+    }
+
+    #[test]
+    fn mocks_can_fail() {
+        let mut sensor = Gp2y1014au::new(MockPin::new(), MockChannel, MockAdc::new(&[1u16]), MockDelay::new());
+        sensor.reader_mut().set_failing(true);
+        assert_eq!(Err(Error::ReadError(MockError)), sensor.read());
+        sensor.reader_mut().set_failing(false);
+        sensor.pin_led_mut().set_failing(true);
+        assert_eq!(Err(Error::LedError(ErrorKind::Other)), sensor.read());
+    }
+}
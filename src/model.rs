@@ -0,0 +1,51 @@
+use crate::calibration::Calibration;
+use crate::timing::Timing;
+use crate::units::Millivolts;
+
+/// Sharp dust sensor models sharing the pulsed LED interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Model {
+    /// The GP2Y1014AU0F, with a typical no-dust voltage of 0.6V.
+    #[default]
+    Gp2y1014au,
+    /// The GP2Y1010AU0F, with a typical no-dust voltage of 0.9V.
+    Gp2y1010au,
+}
+
+impl Model {
+    /// Returns the timing recommended by the datasheet of the model.
+    ///
+    /// Both datasheets specify sampling 0.28ms into a 0.32ms pulse repeated every 10ms.
+    pub const fn timing(self) -> Timing {
+        match self {
+            Model::Gp2y1014au | Model::Gp2y1010au => Timing::datasheet(),
+        }
+    }
+
+    /// Returns the typical calibration from the datasheet of the model.
+    ///
+    /// Both models have a typical sensitivity of 0.5V per 100µg/m³.
+    pub const fn calibration(self) -> Calibration {
+        match self {
+            Model::Gp2y1014au => Calibration::datasheet(),
+            Model::Gp2y1010au => Calibration {
+                v_oc_mv: Millivolts(900.0),
+                sensitivity_mv_per_ug: Calibration::SENSITIVITY_MV_PER_UG,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Model;
+    use crate::calibration::Calibration;
+    use crate::units::Millivolts;
+
+    #[test]
+    fn gp2y1014au_uses_the_datasheet_defaults() {
+        assert_eq!(Calibration::datasheet(), Model::default().calibration());
+        assert_eq!(Millivolts(900.0), Model::Gp2y1010au.calibration().v_oc_mv);
+    }
+}
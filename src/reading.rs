@@ -0,0 +1,140 @@
+use crate::conversion::aqi_from_density;
+use crate::units::{MicrogramsPerM3, Millivolts};
+
+/// A single measurement in all of its representations.
+///
+/// With the `serde` feature enabled, `Serialize` and `Deserialize` are implemented whenever `Word` implements them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reading<Word> {
+    /// The raw ADC reading.
+    pub raw: Word,
+    /// The sensor output voltage.
+    pub voltage_mv: Millivolts,
+    /// The dust density.
+    pub density_ug_m3: MicrogramsPerM3,
+}
+
+/// Formats the reading compactly as `1650 mV / 42 ug/m3`, rounding both values to integers.
+impl<Word> core::fmt::Display for Reading<Word> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:.0} mV / {:.0} ug/m3", self.voltage_mv.0, self.density_ug_m3.0)
+    }
+}
+
+/// A raw reading along with the time it was sampled, see `Gp2y1014au::read_timestamped`.
+///
+/// With the `serde` feature enabled, `Serialize` and `Deserialize` are implemented whenever `Word` implements them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimestampedReading<Word> {
+    /// The time the output was sampled, in the unit of the `Clock`.
+    pub ts: u64,
+    /// The raw ADC reading.
+    pub raw: Word,
+}
+
+/// The raw ADC reading, see `Gp2y1014au::read_as`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Raw<Word>(pub Word);
+
+/// The US EPA air quality index, see `Gp2y1014au::read_as` and `conversion::aqi_from_density`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aqi(pub u16);
+
+/// Types which can be extracted from a `Reading`, this selects the conversion done by `Gp2y1014au::read_as`.
+pub trait FromReading<Word> {
+    /// Extracts the value from `reading`.
+    fn from_reading(reading: &Reading<Word>) -> Self;
+}
+
+impl<Word: Copy> FromReading<Word> for Raw<Word> {
+    fn from_reading(reading: &Reading<Word>) -> Self {
+        Raw(reading.raw)
+    }
+}
+
+/// The sensor output voltage.
+impl<Word> FromReading<Word> for Millivolts {
+    fn from_reading(reading: &Reading<Word>) -> Self {
+        reading.voltage_mv
+    }
+}
+
+/// The dust density.
+impl<Word> FromReading<Word> for MicrogramsPerM3 {
+    fn from_reading(reading: &Reading<Word>) -> Self {
+        reading.density_ug_m3
+    }
+}
+
+impl<Word> FromReading<Word> for Aqi {
+    fn from_reading(reading: &Reading<Word>) -> Self {
+        Aqi(aqi_from_density(reading.density_ug_m3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::string::ToString;
+    use super::{Aqi, FromReading, Raw, Reading};
+    use crate::units::{MicrogramsPerM3, Millivolts};
+
+    #[test]
+    fn reading_displays_voltage_and_density() {
+        let reading = Reading { raw: 2048u16, voltage_mv: Millivolts(1650.2), density_ug_m3: MicrogramsPerM3(41.6) };
+        assert_eq!("1650 mV / 42 ug/m3", reading.to_string());
+    }
+
+    #[test]
+    fn from_reading_extracts_each_representation() {
+        let reading = Reading { raw: 2048u16, voltage_mv: Millivolts(1650.0), density_ug_m3: MicrogramsPerM3(35.4) };
+        assert_eq!(Raw(2048), Raw::from_reading(&reading));
+        assert_eq!(Millivolts(1650.0), Millivolts::from_reading(&reading));
+        assert_eq!(MicrogramsPerM3(35.4), MicrogramsPerM3::from_reading(&reading));
+        assert_eq!(Aqi(100), Aqi::from_reading(&reading));
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::{Reading, TimestampedReading};
+    use crate::units::{MicrogramsPerM3, Millivolts};
+
+    fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+
+    #[test]
+    fn reading_implements_serde() {
+        assert_serde::<Reading<u16>>();
+    }
+
+    #[test]
+    fn timestamped_reading_implements_serde() {
+        assert_serde::<TimestampedReading<u16>>();
+    }
+
+    #[test]
+    fn reading_round_trips_through_json() {
+        let reading = Reading { raw: 2048u16, voltage_mv: Millivolts(1650.5), density_ug_m3: MicrogramsPerM3(42.5) };
+        let mut buffer = [0u8; 96];
+        let len = serde_json_core::to_slice(&reading, &mut buffer).unwrap();
+        let json = core::str::from_utf8(&buffer[..len]).unwrap();
+        assert_eq!(r#"{"raw":2048,"voltage_mv":1650.5,"density_ug_m3":42.5}"#, json);
+        let (decoded, _) = serde_json_core::from_str::<Reading<u16>>(json).unwrap();
+        assert_eq!(reading, decoded);
+    }
+
+    #[test]
+    fn timestamped_reading_round_trips_through_json() {
+        let reading = TimestampedReading { ts: 123_456, raw: 512u16 };
+        let mut buffer = [0u8; 64];
+        let len = serde_json_core::to_slice(&reading, &mut buffer).unwrap();
+        let json = core::str::from_utf8(&buffer[..len]).unwrap();
+        assert_eq!(r#"{"ts":123456,"raw":512}"#, json);
+        let (decoded, _) = serde_json_core::from_str::<TimestampedReading<u16>>(json).unwrap();
+        assert_eq!(reading, decoded);
+    }
+}
@@ -1,202 +1,4024 @@
+use core::convert::TryFrom;
 use core::marker::PhantomData;
-use embedded_hal::digital::v2::OutputPin;
-use embedded_hal::adc::OneShot;
-use embedded_hal::adc::Channel;
+use crate::hal::OutputPin;
+use crate::hal::DelayUs;
+use crate::adc::OneShot;
+use crate::adc::Channel;
+use crate::adc::PowerControl;
+use crate::adc::SharedAdc;
+use crate::calibration::{Calibration, CalibrationCurve, Converter};
+use crate::conversion::{aqi_from_density, particles_per_0_01cf, quality_score, DensityUnit};
+#[cfg(feature = "fixed-point")]
+use crate::fixed::{self, FixedCalibration};
+use crate::filter::{Deadband, ExponentialMovingAverage, HampelFilter, IirLowPass, Kalman1D, MovingAverage, WeightedWindow, WindowShape};
+use crate::reading::{FromReading, Reading, TimestampedReading};
+use crate::clock::Clock;
+use crate::stats::{Histogram, SampleStats, Stats};
+use crate::threshold::{Crossing, Threshold};
+use crate::timing::{NoDelay, Timing};
+use crate::model::Model;
+use crate::diagnostic::{DiagnosticResult, DiagnosticStatus};
+use crate::units::{MicrogramsPerM3, Millivolts};
 
-pub struct Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData>
+/// Driver for the GP2Y1014AU dust sensor.
+///
+/// The sensor is `Send` and `Sync` whenever the LED pin, the data pin, the reader, the delay and `Word` are,
+/// so it can be shared through a `Mutex` like its peripherals. The `Adc` type parameter is only a marker
+/// and does not affect this.
+///
+/// `State` is either `Calibrated`, the default, or `Uncalibrated`. The density methods are only available
+/// on a calibrated sensor, so a sensor created with `new_uncalibrated` has to be calibrated before use.
+///
+/// The methods reading through the owned reader require it to implement `OneShot`. A sensor created with
+/// `new_shared` only offers `read_with` besides the configuration, conversion and accessor methods.
+pub struct Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay, State = Calibrated>
 where 
     PinLed: OutputPin, 
-    OneShotReader: OneShot<Adc, Word, PinData>,
-    PinData: Channel<Adc>
+    PinData: Channel<Adc>,
+    Delay: DelayUs,
 {
     pin_led: PinLed,
     one_shot_reader: OneShotReader,
     pin_data: PinData,
-    _unused: PhantomData<Adc>,
-    _unused2: PhantomData<Word>,
-   
+    delay: Delay,
+    timing: Timing,
+    reference_mv: u16,
+    resolution_bits: u8,
+    divider_ratio: f32,
+    /// `divider_ratio` in thousandths, so the fixed-point conversions apply it with integer math only.
+    #[cfg(feature = "fixed-point")]
+    divider_ratio_milli: u32,
+    samples_per_pulse: u8,
+    discard_first: u8,
+    saturation_margin: u32,
+    calibration: Calibration,
+    led_polarity: LedPolarity,
+    temperature_coefficient_mv_per_c: f32,
+    invert_data: bool,
+    pre_sample: Option<fn(&mut OneShotReader, &mut PinData)>,
+    last_raw: Option<Word>,
+    noise_floor_mv: Option<Millivolts>,
+    measurement_started: bool,
+    retry_read_errors: bool,
+    pulsed: bool,
+    converting: bool,
+    /// The conversions of the pulse of `read_nb` taken so far, including the discarded ones, see `discard_first`.
+    converted: u16,
+    /// The sum of the kept conversions of the pulse of `read_nb`, see `samples_per_pulse`.
+    pulse_sum: u64,
+    _unused: PhantomData<fn() -> (Adc, Word)>,
+    _state: PhantomData<State>,
+}
+
+/// State of a `Gp2y1014au` whose calibration can be trusted, which enables the density methods.
+///
+/// The constructors start in this state with the datasheet calibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibrated;
+
+/// State of a `Gp2y1014au` created with `new_uncalibrated`, which only reads raw words and voltages.
+///
+/// Use `Gp2y1014au::calibrate` or `Gp2y1014au::calibrate_zero` to enable the density methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uncalibrated;
+
+/// Level of the LED pin which turns the sensor's LED on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LedPolarity {
+    /// The LED is on while the pin is low, which is how the bare sensor is wired.
+    #[default]
+    ActiveLow,
+    /// The LED is on while the pin is high, for example when the LED line is driven through an inverting transistor.
+    ActiveHigh,
+}
+
+/// Builder for `Gp2y1014au`, created with `Gp2y1014au::builder`.
+///
+/// Every setting defaults to the datasheet value, or to the width of `Word` for the resolution of narrower ADCs, so
+/// `builder(...).build()` succeeds and is equivalent to `Gp2y1014au::new`.
+pub struct Gp2y1014auBuilder<PinLed, OneShotReader, Adc, Word, PinData, Delay> {
+    pin_led: PinLed,
+    one_shot_reader: OneShotReader,
+    pin_data: PinData,
+    delay: Delay,
+    timing: Timing,
+    reference_mv: Millivolts,
+    resolution_bits: u8,
+    divider_ratio: f32,
+    samples_per_pulse: u8,
+    discard_first: u8,
+    saturation_margin: u32,
+    calibration: Calibration,
+    led_polarity: LedPolarity,
+    temperature_coefficient_mv_per_c: f32,
+    invert_data: bool,
+    pre_sample: Option<fn(&mut OneShotReader, &mut PinData)>,
+    _unused: PhantomData<fn() -> (Adc, Word)>,
+}
+
+/// The peripherals used to construct a sensor, returned by `Gp2y1014au::into_parts`.
+pub struct Parts<PinLed, OneShotReader, PinData, Delay> {
+    /// The pin connected to the led for the sensor.
+    pub pin_led: PinLed,
+    /// The pin connected to data/out on the sensor.
+    pub pin_data: PinData,
+    /// The one shot reader used to sample the data pin.
+    pub reader: OneShotReader,
+    /// The delay used to time the LED pulse.
+    pub delay: Delay,
+}
+
+/// A measurement started with `Gp2y1014au::start_measurement`, which keeps the LED on until it is polled.
+///
+/// Dropping the guard without calling `poll` abandons the measurement and turns the LED off, so it
+/// cannot be left on by accident. To finish the measurement through `Gp2y1014au::poll_measurement`
+/// instead, for example from another interrupt handler, pass the guard to `core::mem::forget`.
+pub struct MeasurementGuard<'a, PinLed, OneShotReader, Adc, Word, PinData, Delay, State = Calibrated>
+where
+    PinLed: OutputPin,
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc>,
+    Word: Copy + Into<u32> + TryFrom<u32>,
+    Delay: DelayUs,
+{
+    sensor: &'a mut Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay, State>,
 }
 
+impl<PinLed, OneShotReader, Adc, Word, PinData, Delay, State> MeasurementGuard<'_, PinLed, OneShotReader, Adc, Word, PinData, Delay, State>
+where
+    PinLed: OutputPin,
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc>,
+    Word: Copy + Into<u32> + TryFrom<u32>,
+    Delay: DelayUs,
+{
+    /// Finishes the measurement, see `Gp2y1014au::poll_measurement`.
+    pub fn poll(self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.sensor.poll_measurement()
+    }
+}
+
+impl<PinLed, OneShotReader, Adc, Word, PinData, Delay, State> Drop for MeasurementGuard<'_, PinLed, OneShotReader, Adc, Word, PinData, Delay, State>
+where
+    PinLed: OutputPin,
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc>,
+    Word: Copy + Into<u32> + TryFrom<u32>,
+    Delay: DelayUs,
+{
+    fn drop(&mut self) {
+        if self.sensor.measurement_started {
+            self.sensor.measurement_started = false;
+            let _ = self.sensor.led_off();
+        }
+    }
+}
+
+/// Errors returned by the sensor.
+///
+/// `Debug`, `Clone`, `Copy`, `PartialEq` and `Eq` are implemented whenever both wrapped error
+/// types implement them. With the `defmt` feature enabled, `defmt::Format` is implemented
+/// the same way.
+///
+/// The enum is non-exhaustive so variants can be added without breaking downstream `match`es,
+/// `is_recoverable` covers the common case of deciding whether to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error<OutputError, AdcError> {
     LedError(OutputError),
-    ReadError(AdcError)
+    ReadError(AdcError),
+    /// `poll_measurement` was called without starting a measurement with `start_measurement`.
+    NotStarted,
+    /// The reading was at the full scale of the ADC, so no meaningful density can be derived from it,
+    /// see `Gp2y1014au::set_saturation_margin`.
+    Saturated,
+    /// The ADC did not finish the conversion within the polls allowed by `Gp2y1014au::read_with_timeout`.
+    Timeout,
+    /// The average voltage of `Gp2y1014au::calibrate_span` was not above the no-dust voltage.
+    BelowBaseline,
+    /// The calibration measured by `Gp2y1014au::calibrate_zero` or `Gp2y1014au::calibrate_span` was rejected
+    /// as by `Gp2y1014au::set_calibration`.
+    InvalidCalibration(ConfigError),
+}
+
+impl<OutputError, AdcError> Error<OutputError, AdcError> {
+    /// Wraps an error of the LED pin, usable as `.map_err(Error::led)`.
+    ///
+    /// `From` cannot be implemented for both wrapped errors since they may be the same type.
+    pub fn led(error: OutputError) -> Self {
+        Error::LedError(error)
+    }
+
+    /// Wraps an error of the ADC, usable as `.map_err(Error::read)`.
+    pub fn read(error: AdcError) -> Self {
+        Error::ReadError(error)
+    }
+
+    /// Returns true if the LED pin failed.
+    pub fn is_led_error(&self) -> bool {
+        matches!(self, Error::LedError(_))
+    }
+
+    /// Returns true if the ADC failed.
+    pub fn is_read_error(&self) -> bool {
+        matches!(self, Error::ReadError(_))
+    }
+
+    /// Returns true if taking the reading again may succeed.
+    ///
+    /// This is the case for `LedError` and `Timeout`. A `ReadError` or `Saturated` reading points at the
+    /// hardware or the configuration, and `NotStarted` at the calling code, so retrying is not expected to help.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, Error::LedError(_) | Error::Timeout)
+    }
+}
+
+/// Receiver of completed measurements, see `Gp2y1014au::poll_into`.
+///
+/// This is the push counterpart of polling `read_nb`, a sink typically pushes the result into a queue consumed
+/// by the main loop. Closures taking the result implement it too.
+pub trait SampleSink<Word, OutputError, AdcError> {
+    /// Called once for every finished measurement, successful or not.
+    fn on_sample(&mut self, result: core::result::Result<Word, Error<OutputError, AdcError>>);
+}
+
+impl<Word, OutputError, AdcError, F> SampleSink<Word, OutputError, AdcError> for F
+where
+    F: FnMut(core::result::Result<Word, Error<OutputError, AdcError>>),
+{
+    fn on_sample(&mut self, result: core::result::Result<Word, Error<OutputError, AdcError>>) {
+        self(result)
+    }
+}
+
+/// Errors returned when validating a configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A smoothing factor was outside of `0.0..=1.0`.
+    AlphaOutOfRange,
+    /// The voltages of a calibration curve were not strictly increasing.
+    PointsNotIncreasing,
+    /// The low limit of a threshold was above its high limit.
+    LowAboveHigh,
+    /// A filter noise parameter was negative, or zero where it must be positive.
+    NoiseOutOfRange,
+    /// The output would be sampled after the end of the LED pulse, or the pulse would outlast the cycle.
+    InvalidTiming,
+    /// A filter cutoff frequency was not positive or not below the Nyquist frequency.
+    CutoffOutOfRange,
+    /// The lower end of a range was not below its upper end.
+    EmptyRange,
+    /// A filter factor or step was negative.
+    FactorOutOfRange,
+    /// The sensitivity of a calibration was not positive, or its no-dust voltage was negative
+    /// or above the highest voltage the ADC can measure.
+    CalibrationOutOfRange,
+    /// The ADC resolution was 0, wider than 32 bits, or wider than the `Word` read from the ADC.
+    ResolutionOutOfRange,
+    /// The ADC reference voltage was 0.
+    ReferenceOutOfRange,
+    /// The voltage divider ratio was below 1.0 or not finite.
+    DividerOutOfRange,
+    /// The number of conversions per LED pulse was 0.
+    SamplesOutOfRange,
 }
 
-impl <PinLed, OneShotReader, Adc, Word, PinData>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData>
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay, State>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay, State>
 where 
     PinLed: OutputPin, 
-    OneShotReader: OneShot<Adc, Word, PinData>,
     PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
 {
-    /// Creates a new instance of the Gp2y1014au dust sensor
-    /// 
-    /// # Arguments
+    /// Default time in microseconds between enabling the LED and sampling the output.
     ///
-    /// * `pin_led`  - The pin connected to the led for the sensor.
-    /// * `pin_data` - The pin connected to data/out on the sensor.
-    /// * `one_shot_reader` - A structure that implements "embedded_hal::adc::OneShot"
+    /// The datasheet specifies the output should be sampled 0.28ms into the LED pulse, see `Timing::datasheet`.
+    pub const SAMPLING_DELAY_US: u32 = Timing::datasheet().sample_delay_us as u32;
+
+    /// Default time in microseconds the LED is kept enabled after the output was sampled.
     ///
-    /// # Example
+    /// Together with `SAMPLING_DELAY_US` this makes up the 0.32ms pulse from the datasheet.
+    pub const SETTLING_DELAY_US: u32 = Timing::datasheet().settling_delay_us() as u32;
+
+    /// Minimum rise of the output with the LED on for `self_test` to pass.
+    pub const SELF_TEST_MIN_RESPONSE_MV: Millivolts = Millivolts(100.0);
+
+    /// Default reference voltage of the ADC.
+    pub const REFERENCE_MV: Millivolts = Millivolts(3300.0);
+
+    /// Default resolution of the ADC in bits.
     ///
-    /// ```ignore
-    /// use stm32f4xx_hal::
-    ///     adc::{
-    ///       Adc,
-    ///       config::AdcConfig
-    ///     };
-    /// 
-    /// // ... 
-    /// let pc1_led = gpioc.pc1.into_push_pull_output();
-    /// let pc0_out = gpioc.pc0.into_analog();
-    /// let mut adc = Adc::adc1(board_peripherals.ADC1, true, AdcConfig::default());
-    /// let mut reader = Gp2y1014au::new(pc1_led, pc0_out, adc);
-    /// ```
-    pub fn new(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader) -> Self {
-        Self {
-            pin_led,
-            one_shot_reader,
-            pin_data,
-            _unused: PhantomData,
-            _unused2: PhantomData,
-        }
-    }
+    /// A `Word` narrower than this defaults to its own width instead, so a sensor reading `u8` words is 8 bits.
+    pub const RESOLUTION_BITS: u8 = 12;
 
-    /// Reads the pin state. Returns back `Word` which varies based on your HAL implementation.
+    /// Default change of the no-dust voltage in millivolts per °C, see `read_dust_density_compensated`.
     ///
-    /// The error types returned back from this will either be `Error::LedError` or `Error::ReadError`.
+    /// This is only a rough typical value, characterize your unit for accurate compensation.
+    pub const TEMPERATURE_COEFFICIENT_MV_PER_C: f32 = 1.0;
+
+    /// Temperature in °C at which the calibrated no-dust voltage applies without compensation.
+    pub const REFERENCE_TEMPERATURE_C: f32 = 25.0;
+
+    /// Returns the timing of the LED pulse.
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Replaces the timing of the LED pulse, which defaults to `Timing::datasheet`.
     ///
-    /// * `Error::ReadError` - Implies the OneShot::read function failed for some reason. `nb::Error::WouldBlock`
-    /// is already handled in the code as a loop.
-    /// * `Error::LedError` - Implies the pin for the LED was either failed to be set low or high respectively. 
-    /// This error indicates you should probably discard the result and call the method again. 
-    pub fn read(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
-        match self.pin_led.set_low() {
-            Ok(()) => (),
-            Err(error) => return Err(Error::LedError(error)),
-        };
-        let result;
-        loop { 
-            let read_result = self.one_shot_reader.read(&mut self.pin_data);
+    /// Returns the error of `Timing::validate` and keeps the current timing if `timing` is invalid.
+    pub fn set_timing(&mut self, timing: Timing) -> core::result::Result<(), ConfigError> {
+        timing.validate()?;
+        self.timing = timing;
+        Ok(())
+    }
 
-            match read_result {
-                Ok(word) => {
-                    result = Ok(word);
-                    break
-                }
-                Err(nb::Error::Other(failed)) => {
-                    result = Err(Error::ReadError(failed));
-                    break
-                }
-                Err(nb::Error::WouldBlock) => continue
-            };
+    /// Returns the reference voltage of the ADC.
+    pub fn reference_mv(&self) -> Millivolts {
+        self.reference_mv.into()
+    }
+
+    /// Sets the reference voltage of the ADC, used to convert readings to voltages.
+    ///
+    /// The reference is rounded to whole millivolts, so the integer conversions of the `fixed-point` feature
+    /// use the same value. Returns `ConfigError::ReferenceOutOfRange` if it rounds to 0, since every voltage
+    /// would be 0, or does not fit a `u16`, and `ConfigError::CalibrationOutOfRange` if the calibration would
+    /// be rejected with the new reference, as by `set_calibration`. The current reference is kept in both cases.
+    pub fn set_reference_mv(&mut self, reference_mv: Millivolts) -> core::result::Result<(), ConfigError> {
+        let reference_mv = check_reference_mv(reference_mv)?;
+        let previous = core::mem::replace(&mut self.reference_mv, reference_mv);
+        if let Err(error) = self.check_calibration(&self.calibration) {
+            self.reference_mv = previous;
+            return Err(error);
         }
-        match self.pin_led.set_high() {
-            Ok(()) => (),
-            Err(error) => return Err(Error::LedError(error)),
-        };
+        Ok(())
+    }
 
-        result
-    }    
+    /// Returns `RESOLUTION_BITS`, capped to the width of `Word`.
+    fn default_resolution_bits() -> u8 {
+        (core::mem::size_of::<Word>() * 8).min(Self::RESOLUTION_BITS as usize) as u8
+    }
 
-    /// Returns back the pins and reader used to construct the sensor.
-    /// This function consumes self.
-    pub fn split(self) -> (PinLed, PinData, OneShotReader) {
-        (self.pin_led, self.pin_data, self.one_shot_reader)
+    /// Returns the resolution of the ADC in bits.
+    pub fn resolution_bits(&self) -> u8 {
+        self.resolution_bits
     }
 
-    
-}
+    /// Sets the resolution of the ADC in bits, used to convert readings to voltages.
+    ///
+    /// Returns `ConfigError::ResolutionOutOfRange` and keeps the current resolution if `resolution_bits` is 0,
+    /// since no reading could be scaled, or wider than `Word` or than the 32 bits of the conversions.
+    pub fn set_resolution_bits(&mut self, resolution_bits: u8) -> core::result::Result<(), ConfigError> {
+        if resolution_bits == 0 || resolution_bits > 32 || resolution_bits as usize > core::mem::size_of::<Word>() * 8 {
+            return Err(ConfigError::ResolutionOutOfRange);
+        }
+        self.resolution_bits = resolution_bits;
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use core::marker::PhantomData;
-    use embedded_hal::digital::v2::OutputPin;
-    use embedded_hal::adc::OneShot;
-    use embedded_hal::adc::Channel;
-    struct BadState;
-    struct GoodState;
-    struct TestAdc {
-        _garbage: bool 
+    /// Returns the ratio of the voltage divider between the sensor output and the ADC input.
+    pub fn divider_ratio(&self) -> f32 {
+        self.divider_ratio
     }
 
-    impl TestAdc {
-        fn new() -> Self {
-            Self {_garbage: true}
+    /// Sets the ratio of the voltage divider between the sensor output and the ADC input, defaults to 1.0.
+    ///
+    /// Voltages are multiplied by this ratio, so a divider made of a 10kΩ and a 20kΩ resistor
+    /// feeding 2/3 of the output to the ADC has a ratio of 1.5.
+    ///
+    /// Returns `ConfigError::DividerOutOfRange` if `divider_ratio` is below 1.0, since a divider only attenuates,
+    /// or not finite, and `ConfigError::CalibrationOutOfRange` if the calibration would be rejected with the new
+    /// ratio, as by `set_calibration`. The current ratio is kept in both cases.
+    pub fn set_divider_ratio(&mut self, divider_ratio: f32) -> core::result::Result<(), ConfigError> {
+        check_divider_ratio(divider_ratio)?;
+        let previous = core::mem::replace(&mut self.divider_ratio, divider_ratio);
+        if let Err(error) = self.check_calibration(&self.calibration) {
+            self.divider_ratio = previous;
+            return Err(error);
         }
+        self.store_divider_ratio_milli();
+        Ok(())
     }
-    struct TestAnalogPin<STATE> {
-        _unused: PhantomData<STATE>
+
+    /// Updates `divider_ratio_milli` after `divider_ratio` changed.
+    fn store_divider_ratio_milli(&mut self) {
+        #[cfg(feature = "fixed-point")]
+        {
+            self.divider_ratio_milli = libm::roundf(self.divider_ratio * 1000.0) as u32;
+        }
     }
-    impl <STATE> TestAnalogPin<STATE> {
-        fn new() -> Self {
-            Self { _unused: PhantomData }
+
+    /// Returns the number of conversions averaged within each LED pulse.
+    pub fn samples_per_pulse(&self) -> u8 {
+        self.samples_per_pulse
+    }
+
+    /// Sets the number of conversions averaged within each LED pulse, defaults to 1.
+    ///
+    /// All conversions are taken back to back after the sampling delay, before the LED is turned off, so
+    /// the duty cycle stays the same. `read` returns their average rounded to the nearest code, and so does
+    /// every method taking readings, from `read_median` to `read_dust_density`. The first ADC error aborts
+    /// the pulse and turns the LED off.
+    ///
+    /// Returns `ConfigError::SamplesOutOfRange` and keeps the current number if `samples_per_pulse` is 0.
+    pub fn set_samples_per_pulse(&mut self, samples_per_pulse: u8) -> core::result::Result<(), ConfigError> {
+        if samples_per_pulse == 0 {
+            return Err(ConfigError::SamplesOutOfRange);
         }
+        self.samples_per_pulse = samples_per_pulse;
+        Ok(())
     }
-    
-    struct TestOutputPin<STATE> {
-        _unused: PhantomData<STATE>
+
+    /// Returns the number of conversions discarded at the start of each LED pulse.
+    pub fn discard_first(&self) -> u8 {
+        self.discard_first
     }
 
+    /// Sets the number of conversions taken and discarded at the start of each LED pulse, defaults to 0.
+    ///
+    /// This lets the sample-and-hold of the ADC settle on the data pin before the conversions which are kept.
+    /// The discarded conversions are taken right after the sampling delay, so the kept ones are slightly later
+    /// within the pulse. It applies to every reading of the data pin by the owned reader with the LED on, including
+    /// `read_with_timeout`, `read_with_yield` and `read_nb`, which poll the discarded conversions like the kept one.
+    pub fn set_discard_first(&mut self, discard_first: u8) {
+        self.discard_first = discard_first;
+    }
 
-    impl <STATE> TestOutputPin<STATE> {
-        fn new() -> Self { 
-            Self { _unused: PhantomData }
-        }
+    /// Returns the number of codes below the full scale of the ADC which are considered saturated.
+    pub fn saturation_margin(&self) -> u32 {
+        self.saturation_margin
     }
 
-    impl OutputPin for TestOutputPin<GoodState> {
-        type Error = ();
-        fn set_high(&mut self) -> Result<(), Self::Error> {
-            Ok(())
-        }
-        fn set_low(&mut self) -> Result<(), Self::Error> {
-            Ok(())
+    /// Sets the number of codes below the full scale of the ADC which are considered saturated, defaults to 0.
+    ///
+    /// The density methods return `Error::Saturated` instead of a density for readings within `saturation_margin`
+    /// of the highest code, so a pegged output is not mistaken for a huge dust density. With the default only
+    /// the highest code itself is considered saturated.
+    pub fn set_saturation_margin(&mut self, saturation_margin: u32) {
+        self.saturation_margin = saturation_margin;
+    }
+
+    /// Returns true if the output is read through an inverting stage.
+    pub fn invert_data(&self) -> bool {
+        self.invert_data
+    }
+
+    /// Sets whether the output is read through an inverting stage, defaults to false.
+    ///
+    /// Some breakout boards buffer the output with an inverting amplifier, so more dust yields a lower reading.
+    /// When enabled, the voltage conversions compute the reference voltage minus the measured voltage, and readings
+    /// within the saturation margin of the lowest code are considered saturated instead of the highest code.
+    pub fn set_invert_data(&mut self, invert_data: bool) {
+        self.invert_data = invert_data;
+    }
+
+    /// Returns the level of the LED pin which turns the LED on.
+    pub fn led_polarity(&self) -> LedPolarity {
+        self.led_polarity
+    }
+
+    /// Sets the level of the LED pin which turns the LED on, defaults to `LedPolarity::ActiveLow`.
+    pub fn set_led_polarity(&mut self, led_polarity: LedPolarity) {
+        self.led_polarity = led_polarity;
+    }
+
+    /// Returns the change of the no-dust voltage in millivolts per °C.
+    pub fn temperature_coefficient_mv_per_c(&self) -> f32 {
+        self.temperature_coefficient_mv_per_c
+    }
+
+    /// Sets the change of the no-dust voltage in millivolts per °C, see `read_dust_density_compensated`.
+    pub fn set_temperature_coefficient_mv_per_c(&mut self, temperature_coefficient_mv_per_c: f32) {
+        self.temperature_coefficient_mv_per_c = temperature_coefficient_mv_per_c;
+    }
+
+    /// Returns the calibration used to convert voltages to dust density.
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    /// Checks `calibration` against the reference voltage and divider ratio, see `set_calibration`.
+    fn check_calibration(&self, calibration: &Calibration) -> core::result::Result<(), ConfigError> {
+        calibration.validate()?;
+        if calibration.v_oc_mv.0 > self.reference_mv as f32 * self.divider_ratio {
+            return Err(ConfigError::CalibrationOutOfRange);
         }
+        Ok(())
     }
-    impl OutputPin for TestOutputPin<BadState> {
-        type Error = ();
-        fn set_high(&mut self) -> Result<(), Self::Error> {
-            Err(())
+
+    /// Takes a reading like `read`, but converts with `adc` instead of the reader owned by the sensor.
+    ///
+    /// This lets several sensors share a single ADC, see `new_shared`. The `samples_per_pulse` conversions are
+    /// averaged as for `read`. The error types are the same as for `read`, with `Error::ReadError` wrapping the
+    /// error of `adc`.
+    pub fn read_with<R>(&mut self, adc: &mut R) -> core::result::Result<Word, Error<PinLed::Error, R::Error>>
+    where
+        R: OneShot<Adc, Word, PinData>,
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let pin_data = &mut self.pin_data;
+        let result = average_conversions(self.samples_per_pulse, || sample(adc, pin_data))
+            .map_err(Error::ReadError)
+            .map(|word| self.remember(word));
+        self.end_pulse(result.is_ok()).map_err(Error::LedError)?;
+
+        result
+    }
+
+    /// Sets a hook called right before every conversion of the data pin by the owned reader, or removes it with `None`.
+    ///
+    /// This is meant to configure the ADC for this channel, for example to lengthen its sample time so the
+    /// output of the sensor is fully acquired. Too short an acquisition time yields systematically low readings.
+    /// The hook is not called by `read_with` and `read_on`, which do not use the owned reader and data pin.
+    pub fn set_pre_sample(&mut self, pre_sample: Option<fn(&mut OneShotReader, &mut PinData)>) {
+        self.pre_sample = pre_sample;
+    }
+
+    fn remember(&mut self, word: Word) -> Word {
+        self.last_raw = Some(word);
+        word
+    }
+
+    /// Returns the last reading taken with the LED on, or `None` if no reading succeeded yet.
+    ///
+    /// Every reading method updates it, so several consumers can share one measurement without pulsing the
+    /// LED again. When several conversions are averaged within a pulse, this is their average as returned by `read`.
+    pub fn last_raw(&self) -> Option<Word> {
+        self.last_raw
+    }
+
+    /// Turns the LED on and waits until the output should be sampled.
+    fn begin_pulse(&mut self) -> core::result::Result<(), PinLed::Error> {
+        match self.led_on() {
+            Ok(()) => (),
+            Err(error) => {
+                let _ = self.led_off();
+                return Err(error)
+            }
+        };
+        self.measurement_started = false;
+        self.converting = false;
+        self.delay.delay_us(self.timing.sample_delay_us.into());
+        Ok(())
+    }
+
+    /// Turns the LED off, after the settling delay if the output was sampled successfully.
+    fn end_pulse(&mut self, sampled: bool) -> core::result::Result<(), PinLed::Error> {
+        if sampled {
+            self.delay.delay_us(self.timing.settling_delay_us().into());
         }
-        fn set_low(&mut self) -> Result<(), Self::Error> {
-            Err(())
+        self.led_off()
+    }
+
+    /// Sets whether `read_with_retries` also retries `Error::ReadError`, which is disabled by default since ADC
+    /// errors are usually persistent.
+    pub fn set_retry_read_errors(&mut self, retry_read_errors: bool) {
+        self.retry_read_errors = retry_read_errors;
+    }
+
+    /// Waits for the rest of the cycle after the previous pulse, see `read_periodic`.
+    fn wait_for_cycle(&mut self) {
+        if self.pulsed {
+            let pulse_width_us: u32 = self.timing.pulse_width_us.into();
+            self.delay.delay_us(self.timing.cycle_period_us.saturating_sub(pulse_width_us));
         }
+        self.pulsed = true;
     }
-    impl <STATE> Channel<TestAdc> for TestAnalogPin<STATE> {
-        type ID = u8;
-        fn channel() -> Self::ID {
-            return 1;
+
+    /// Clears the state kept between readings, for example after waking from deep sleep.
+    ///
+    /// This abandons a measurement in progress, turning the LED off, forgets `last_raw` and `last_reading`, and makes the next
+    /// `read_periodic` or `measure` pulse right away. The configuration, the calibration and the noise floor
+    /// are kept. Filters are owned by the caller and have their own `reset`.
+    pub fn reset(&mut self) {
+        self.abandon_measurement();
+        self.last_raw = None;
+        self.pulsed = false;
+    }
+
+    /// Turns the LED off if a measurement started with `start_measurement` or `read_nb` was not finished.
+    fn abandon_measurement(&mut self) {
+        if self.measurement_started || self.converting {
+            self.measurement_started = false;
+            self.converting = false;
+            let _ = self.led_off();
         }
     }
 
-    impl OneShot<TestAdc, u8, TestAnalogPin<GoodState>> for TestAdc {
-        type Error = ();
-        fn read(&mut self, _: &mut TestAnalogPin<GoodState>) -> nb::Result<u8, ()> {
-            Ok(10u8)
+    /// Returns true if a measurement was started with `start_measurement` and not polled yet.
+    pub fn is_measurement_started(&self) -> bool {
+        self.measurement_started
+    }
+
+    fn led_on(&mut self) -> core::result::Result<(), PinLed::Error> {
+        match self.led_polarity {
+            LedPolarity::ActiveLow => self.pin_led.set_low(),
+            LedPolarity::ActiveHigh => self.pin_led.set_high(),
         }
     }
-    impl OneShot<TestAdc, u8, TestAnalogPin<BadState>> for TestAdc {
-        type Error = ();
-        fn read(&mut self, _: &mut TestAnalogPin<BadState>) -> nb::Result<u8, ()> {
-            Err(nb::Error::Other(()))
+
+    fn led_off(&mut self) -> core::result::Result<(), PinLed::Error> {
+        match self.led_polarity {
+            LedPolarity::ActiveLow => self.pin_led.set_high(),
+            LedPolarity::ActiveHigh => self.pin_led.set_low(),
         }
     }
-    #[test]
-    fn read_returns_value_when_no_errors_present() {
-        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
-        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
-        let test_adc: TestAdc = TestAdc::new();
-        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc);
-        assert_eq!(10u8, sensor.read().ok().unwrap() );
+
+    /// Converts a raw reading taken outside of the driver to a voltage, with the same math as `read_voltage`.
+    ///
+    /// The hardware is not touched, only the reference voltage, resolution and divider ratio are used.
+    /// This is meant for readings captured by other means, such as DMA, or for replaying logged data.
+    pub fn voltage_from_raw(&self, raw: Word) -> Millivolts
+    where
+        Word: Into<u32>,
+    {
+        self.voltage_from(raw.into())
     }
 
-    #[test]
-    fn read_returns_error_when_one_shot_read_fails() {
-        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
-        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
-        let test_adc: TestAdc = TestAdc::new();
-        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc);
-        sensor.read().expect_err("Expected this function to error");
+    /// Readings above the full scale of the ADC saturate at the highest code, so a reading wider than
+    /// `resolution_bits` does not yield voltages above the reference.
+    fn voltage_from(&self, raw: u32) -> Millivolts {
+        let full_scale = 1u64 << self.resolution_bits;
+        let raw = (raw as u64).min(full_scale - 1);
+        let measured_mv = raw as f32 * self.reference_mv as f32 / full_scale as f32;
+        let output_mv = if self.invert_data { self.reference_mv as f32 - measured_mv } else { measured_mv };
+        Millivolts(output_mv * self.divider_ratio)
+    }
+
+    fn voltage_from_f64(&self, raw: u32) -> f64 {
+        let full_scale = 1u64 << self.resolution_bits;
+        let raw = (raw as u64).min(full_scale - 1);
+        let measured_mv = raw as f64 * self.reference_mv as f64 / full_scale as f64;
+        let output_mv = if self.invert_data { self.reference_mv as f64 - measured_mv } else { measured_mv };
+        output_mv * self.divider_ratio as f64
+    }
+
+    #[cfg(feature = "fixed-point")]
+    fn voltage_mv_fixed(&self, raw: u32) -> i32 {
+        let measured_mv = fixed::voltage_mv(raw, self.reference_mv, self.resolution_bits);
+        let output_mv = if self.invert_data { self.reference_mv as i32 - measured_mv } else { measured_mv };
+        ((output_mv as i64 * self.divider_ratio_milli as i64 + 500) / 1000) as i32
+    }
+
+    /// Returns the noise floor stored by `measure_noise_floor`, or `None` if it was never measured.
+    pub fn noise_floor_mv(&self) -> Option<Millivolts> {
+        self.noise_floor_mv
+    }
+
+    /// Returns a reference to the LED pin.
+    pub fn pin_led(&self) -> &PinLed {
+        &self.pin_led
+    }
+
+    /// Returns a mutable reference to the LED pin.
+    ///
+    /// Changing the pin level during a measurement started with `start_measurement` affects the LED pulse.
+    pub fn pin_led_mut(&mut self) -> &mut PinLed {
+        &mut self.pin_led
+    }
+
+    /// Returns a reference to the data pin.
+    pub fn pin_data(&self) -> &PinData {
+        &self.pin_data
+    }
+
+    /// Returns a mutable reference to the data pin.
+    pub fn pin_data_mut(&mut self) -> &mut PinData {
+        &mut self.pin_data
+    }
+
+    /// Swaps in a new LED pin and returns the old one, for example to reconfigure it while sleeping.
+    ///
+    /// The reader, delay and configuration are kept. The new pin is expected in the inactive state,
+    /// it is not driven until the next reading.
+    pub fn replace_pin_led(&mut self, pin_led: PinLed) -> PinLed {
+        core::mem::replace(&mut self.pin_led, pin_led)
+    }
+
+    /// Swaps in a new data pin and returns the old one, see `replace_pin_led`.
+    pub fn replace_pin_data(&mut self, pin_data: PinData) -> PinData {
+        core::mem::replace(&mut self.pin_data, pin_data)
+    }
+
+    /// Returns a reference to the one shot reader.
+    pub fn reader(&self) -> &OneShotReader {
+        &self.one_shot_reader
+    }
+
+    /// Returns a mutable reference to the one shot reader, for example to reconfigure the ADC between readings.
+    pub fn reader_mut(&mut self) -> &mut OneShotReader {
+        &mut self.one_shot_reader
+    }
+
+    /// Returns a reference to the delay.
+    pub fn delay(&self) -> &Delay {
+        &self.delay
+    }
+
+    /// Returns a mutable reference to the delay.
+    pub fn delay_mut(&mut self) -> &mut Delay {
+        &mut self.delay
+    }
+
+    fn into_state<NewState>(self) -> Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay, NewState> {
+        Gp2y1014au {
+            pin_led: self.pin_led,
+            one_shot_reader: self.one_shot_reader,
+            pin_data: self.pin_data,
+            delay: self.delay,
+            timing: self.timing,
+            reference_mv: self.reference_mv,
+            resolution_bits: self.resolution_bits,
+            divider_ratio: self.divider_ratio,
+            #[cfg(feature = "fixed-point")]
+            divider_ratio_milli: self.divider_ratio_milli,
+            samples_per_pulse: self.samples_per_pulse,
+            discard_first: self.discard_first,
+            saturation_margin: self.saturation_margin,
+            calibration: self.calibration,
+            led_polarity: self.led_polarity,
+            temperature_coefficient_mv_per_c: self.temperature_coefficient_mv_per_c,
+            invert_data: self.invert_data,
+            pre_sample: self.pre_sample,
+            last_raw: self.last_raw,
+            noise_floor_mv: self.noise_floor_mv,
+            measurement_started: self.measurement_started,
+            retry_read_errors: self.retry_read_errors,
+            pulsed: self.pulsed,
+            converting: self.converting,
+            converted: self.converted,
+            pulse_sum: self.pulse_sum,
+            _unused: PhantomData,
+            _state: PhantomData,
+        }
+    }
+
+    /// Returns back the pins, reader and delay used to construct the sensor.
+    /// This function consumes self.
+    ///
+    /// If a measurement is still in progress, for example a `read_nb` waiting for the ADC, the LED is turned
+    /// off first so the pins are not handed back with the LED on. An error turning it off is ignored.
+    ///
+    /// Prefer `into_parts`, which names the returned peripherals so the two pins cannot be swapped by accident.
+    pub fn split(self) -> (PinLed, PinData, OneShotReader, Delay) {
+        let parts = self.into_parts();
+        (parts.pin_led, parts.pin_data, parts.reader, parts.delay)
+    }
+
+    /// Returns back the pins, reader and delay used to construct the sensor as named fields.
+    /// This function consumes self.
+    ///
+    /// Like `split`, the LED is turned off first if a measurement is still in progress.
+    pub fn into_parts(mut self) -> Parts<PinLed, OneShotReader, PinData, Delay> {
+        self.abandon_measurement();
+        Parts {
+            pin_led: self.pin_led,
+            pin_data: self.pin_data,
+            reader: self.one_shot_reader,
+            delay: self.delay,
+        }
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay, State>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay, State>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc> ,
+    Word: Copy + Into<u32> + TryFrom<u32>,
+    Delay: DelayUs,
+{
+    /// Reads the pin state. Returns back `Word` which varies based on your HAL implementation.
+    ///
+    /// This is the low-level primitive, `measure` is the recommended way to take a reading.
+    ///
+    /// The LED is enabled for one pulse: the output is sampled `Timing::sample_delay_us` after enabling it,
+    /// and the LED is disabled again at the end of `Timing::pulse_width_us`. The reading is the average of
+    /// the `samples_per_pulse` conversions taken within the pulse, rounded to the nearest code.
+    ///
+    /// The error types returned back from this will either be `Error::LedError` or `Error::ReadError`.
+    ///
+    /// * `Error::ReadError` - Implies the OneShot::read function failed for some reason. `nb::Error::WouldBlock`
+    ///   is already handled in the code as a loop.
+    /// * `Error::LedError` - Implies the pin for the LED was either failed to be turned on or off respectively. 
+    ///   This error indicates you should probably discard the result and call the method again. 
+    ///
+    /// The LED is always turned off before returning, also when an error occurred. The only exception
+    /// is turning it off failing itself, which is reported as `Error::LedError`. If the ADC fails, the
+    /// LED is turned off right away instead of at the end of the pulse.
+    ///
+    /// The ADC is polled until it finishes the conversion, which never returns if it is misconfigured.
+    /// Unless a watchdog covers that case, prefer `read_with_timeout`.
+    pub fn read(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let result = self.convert_pulse().map_err(Error::ReadError);
+        self.end_pulse(result.is_ok()).map_err(Error::LedError)?;
+
+        result
+    }    
+
+    /// Reads the output with the LED kept off, to measure the dark offset and ambient light.
+    ///
+    /// The LED pin is not touched, so it stays in the inactive state left by the previous reading. The output
+    /// is sampled with the same delay as `read`, so the result can be subtracted from a normal reading to get
+    /// the part of the signal caused by the LED. Only `Error::ReadError` is returned.
+    pub fn read_dark(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.delay.delay_us(self.timing.sample_delay_us.into());
+        self.read_channel_raw().map_err(Error::ReadError)
+    }
+
+    /// Converts the data pin once, without pulsing the LED or waiting. Meant as a diagnostic.
+    ///
+    /// This only confirms the ADC and the data pin are wired up, for example during board bring-up.
+    /// The result is not a dust measurement, use `read` for that. The error of the reader is returned as is.
+    pub fn read_channel_raw(&mut self) -> core::result::Result<Word, OneShotReader::Error> {
+        self.run_pre_sample();
+        sample(&mut self.one_shot_reader, &mut self.pin_data)
+    }
+
+    /// Takes a reading like `read` and tags it with the time from `clock`.
+    ///
+    /// The time is taken right before the ADC conversion starts, after the sampling delay, so it marks the
+    /// moment the output was sampled. The error types are the same as for `read`.
+    pub fn read_timestamped<C: Clock>(&mut self, clock: &C) -> core::result::Result<TimestampedReading<Word>, Error<PinLed::Error, OneShotReader::Error>> {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let ts = clock.now();
+        let result = self.convert_pulse().map_err(Error::ReadError);
+        self.end_pulse(result.is_ok()).map_err(Error::LedError)?;
+
+        Ok(TimestampedReading { ts, raw: result? })
+    }
+
+    /// Takes a reading like `read`, giving up once the ADC returned `nb::Error::WouldBlock` `max_polls` times in a row.
+    ///
+    /// Returns `Error::Timeout` in that case, after turning the LED off. This is the recommended way to read
+    /// the sensor unless a watchdog resets the firmware if the ADC hangs.
+    pub fn read_with_timeout(&mut self, max_polls: u32) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let mut convert = |reader: &mut OneShotReader, pin: &mut PinData| sample_bounded(reader, pin, max_polls);
+        let result = self.convert_pulse_with(&mut convert).map_err(|error| match error {
+            nb::Error::Other(error) => Error::ReadError(error),
+            nb::Error::WouldBlock => Error::Timeout,
+        });
+        self.end_pulse(result.is_ok()).map_err(Error::LedError)?;
+
+        result
+    }
+
+    /// Takes a reading like `read`, calling `on_block` every time the ADC returns `nb::Error::WouldBlock`.
+    ///
+    /// This lets a cooperative scheduler run other tasks during the conversion, for example by passing its
+    /// `yield_now`. The LED stays on meanwhile, so `on_block` should return quickly. The error types are the
+    /// same as for `read`.
+    pub fn read_with_yield(&mut self, mut on_block: impl FnMut()) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let mut convert = |reader: &mut OneShotReader, pin: &mut PinData| sample_yielding(reader, pin, &mut on_block);
+        let result = self.convert_pulse_with(&mut convert).map_err(Error::ReadError);
+        self.end_pulse(result.is_ok()).map_err(Error::LedError)?;
+
+        result
+    }
+
+    /// Takes a reading like `read`, but keeps the reading if only turning the LED off failed.
+    ///
+    /// The error of turning the LED off is returned along with the reading instead, so it can be logged
+    /// separately. Failing to turn the LED on or to read the ADC is still returned as an error.
+    #[allow(clippy::type_complexity)]
+    pub fn read_keep_value(&mut self) -> core::result::Result<(Word, Option<PinLed::Error>), Error<PinLed::Error, OneShotReader::Error>> {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let result = self.convert_pulse().map_err(Error::ReadError);
+        let led_off = self.end_pulse(result.is_ok());
+        let word = result?;
+        Ok((word, led_off.err()))
+    }
+
+    /// Takes a reading like `read`, but samples `channel` instead of the data pin owned by the sensor.
+    ///
+    /// This is meant for setups where one ADC reads the outputs of several sensors, for example through
+    /// an analog multiplexer. Routing `channel` to this sensor's output is left to the caller. The
+    /// `samples_per_pulse` conversions are averaged as for `read`. The error types are the same as for `read`,
+    /// with `Error::ReadError` wrapping the error of the reader for `channel`.
+    #[allow(clippy::type_complexity)]
+    pub fn read_on<C>(&mut self, channel: &mut C) -> core::result::Result<Word, Error<PinLed::Error, <OneShotReader as OneShot<Adc, Word, C>>::Error>>
+    where
+        C: Channel<Adc>,
+        OneShotReader: OneShot<Adc, Word, C>,
+    {
+        self.begin_pulse().map_err(Error::LedError)?;
+        let reader = &mut self.one_shot_reader;
+        let result = average_conversions(self.samples_per_pulse, || sample(reader, channel))
+            .map_err(Error::ReadError)
+            .map(|word| self.remember(word));
+        self.end_pulse(result.is_ok()).map_err(Error::LedError)?;
+
+        result
+    }
+
+    /// Takes the conversions of a pulse with the owned reader and data pin, caching the average for `last_raw`.
+    fn convert_pulse(&mut self) -> core::result::Result<Word, OneShotReader::Error> {
+        self.convert_pulse_with(&mut sample)
+    }
+
+    /// Takes the conversions of `convert_pulse`, waiting for each one with `convert`.
+    ///
+    /// The `discard_first` conversions are dropped, the `samples_per_pulse` following ones are averaged.
+    fn convert_pulse_with<E>(&mut self, convert: &mut impl FnMut(&mut OneShotReader, &mut PinData) -> core::result::Result<Word, E>) -> core::result::Result<Word, E> {
+        for _ in 0..self.discard_first {
+            self.run_pre_sample();
+            convert(&mut self.one_shot_reader, &mut self.pin_data)?;
+        }
+        let word = average_conversions(self.samples_per_pulse, || {
+            self.run_pre_sample();
+            convert(&mut self.one_shot_reader, &mut self.pin_data)
+        })?;
+        Ok(self.remember(word))
+    }
+
+    fn run_pre_sample(&mut self) {
+        if let Some(pre_sample) = self.pre_sample {
+            pre_sample(&mut self.one_shot_reader, &mut self.pin_data);
+        }
+    }
+
+    /// Takes a reading like `read`, retrying the whole pulse up to `max` times if it fails.
+    ///
+    /// Only `Error::LedError` is retried, unless retrying `Error::ReadError` was enabled with
+    /// `set_retry_read_errors`. On success the reading is returned along with the number of retries
+    /// it took, otherwise the error of the last attempt is returned.
+    pub fn read_with_retries(&mut self, max: u8) -> core::result::Result<(Word, u8), Error<PinLed::Error, OneShotReader::Error>> {
+        let mut retries = 0;
+        loop {
+            let error = match self.read() {
+                Ok(word) => return Ok((word, retries)),
+                Err(error) => error,
+            };
+            let retry = match &error {
+                Error::LedError(_) => true,
+                Error::ReadError(_) => self.retry_read_errors,
+                _ => false,
+            };
+            if !retry || retries >= max {
+                return Err(error);
+            }
+            retries += 1;
+        }
+    }
+
+    /// Takes a reading like `read`, first waiting so pulses are at least `Timing::cycle_period_us` apart.
+    ///
+    /// The first call pulses the LED right away. Since the delay cannot measure the time spent between calls,
+    /// later calls wait for the whole rest of the cycle after the previous pulse, so the actual period is
+    /// the cycle period plus the time spent by the caller.
+    pub fn read_periodic(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        self.wait_for_cycle();
+        self.read()
+    }
+
+    /// Fills `buf` with consecutive readings taken with `read_periodic`, so they are a cycle period apart.
+    ///
+    /// Returns the number of readings written, which is the length of `buf` on success. The first error
+    /// encountered is returned, the readings taken before it are left at the start of `buf`.
+    pub fn read_batch(&mut self, buf: &mut [Word]) -> core::result::Result<usize, Error<PinLed::Error, OneShotReader::Error>> {
+        for slot in buf.iter_mut() {
+            *slot = self.read_periodic()?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Takes `discard` readings and throws them away, to let the sensor settle after power-up.
+    ///
+    /// Each reading pulses the LED with the same timing as `read`. The first error encountered is returned
+    /// and the remaining readings are not taken.
+    pub fn warm_up(&mut self, discard: u16) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>> {
+        for _ in 0..discard {
+            self.read()?;
+        }
+        Ok(())
+    }
+
+    /// Returns an endless iterator taking one reading per call to `next`, see `read`.
+    ///
+    /// No reading is taken until `next` is called, so adapters like `take` only pulse the LED as often as needed.
+    pub fn iter_readings(&mut self) -> impl Iterator<Item = core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>>> + '_ {
+        core::iter::from_fn(move || Some(self.read()))
+    }
+
+    /// Starts a measurement by turning the LED on, without waiting or sampling.
+    ///
+    /// Call `MeasurementGuard::poll` on the returned guard once `Timing::sample_delay_us` elapsed, for example
+    /// from a timer interrupt, to finish the measurement. This lets you do other work during the LED pulse.
+    /// Dropping the guard instead turns the LED off again.
+    ///
+    /// If turning the LED on fails `Error::LedError` is returned, the LED is turned off and no measurement is started.
+    #[allow(clippy::type_complexity)]
+    pub fn start_measurement(&mut self) -> core::result::Result<MeasurementGuard<'_, PinLed, OneShotReader, Adc, Word, PinData, Delay, State>, Error<PinLed::Error, OneShotReader::Error>> {
+        match self.led_on() {
+            Ok(()) => (),
+            Err(error) => {
+                let _ = self.led_off();
+                return Err(Error::LedError(error))
+            }
+        };
+        self.measurement_started = true;
+        Ok(MeasurementGuard { sensor: self })
+    }
+
+    /// Finishes a measurement started with `start_measurement` by sampling the output and turning the LED off.
+    ///
+    /// The LED is turned off right after sampling, no settling delay is applied. Returns `Error::NotStarted`
+    /// if no measurement was started, otherwise the error types are the same as for `read`.
+    pub fn poll_measurement(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        if !self.measurement_started {
+            return Err(Error::NotStarted);
+        }
+        self.measurement_started = false;
+        let result = self.convert_pulse().map_err(Error::ReadError);
+        match self.led_off() {
+            Ok(()) => (),
+            Err(error) => return Err(Error::LedError(error)),
+        };
+
+        result
+    }
+
+    /// Takes a reading like `read`, returning `nb::Error::WouldBlock` while the ADC conversion is pending
+    /// instead of spinning on it.
+    ///
+    /// The first call turns the LED on and waits the sampling delay, later calls keep polling the ADC
+    /// until it returns a reading. The LED is then turned off as for `read`, so call this until it returns
+    /// something other than `WouldBlock`. Only the ADC conversion is non-blocking, the delays are still waited.
+    pub fn read_nb(&mut self) -> nb::Result<Word, Error<PinLed::Error, OneShotReader::Error>> {
+        if !self.converting {
+            self.begin_pulse().map_err(|error| nb::Error::Other(Error::LedError(error)))?;
+            self.run_pre_sample();
+            self.converting = true;
+            self.converted = 0;
+            self.pulse_sum = 0;
+        }
+        let discard_first = u16::from(self.discard_first);
+        let count = self.samples_per_pulse.max(1);
+        let result = loop {
+            match self.one_shot_reader.read(&mut self.pin_data) {
+                Ok(_) if self.converted < discard_first => (),
+                Ok(word) => {
+                    self.pulse_sum += u64::from(word.into());
+                    if self.converted + 1 - discard_first >= u16::from(count) {
+                        break Ok(self.remember(average_word(self.pulse_sum, count, word)));
+                    }
+                }
+                Err(nb::Error::Other(error)) => break Err(Error::ReadError(error)),
+                Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+            }
+            self.converted += 1;
+            self.run_pre_sample();
+        };
+        self.converting = false;
+        self.end_pulse(result.is_ok()).map_err(|error| nb::Error::Other(Error::LedError(error)))?;
+
+        result.map_err(nb::Error::Other)
+    }
+
+    /// Advances a measurement like `read_nb` and passes the result to `sink` once it is finished.
+    ///
+    /// Returns true if `sink` was called, false while the ADC conversion is still pending. Call this from
+    /// a timer interrupt or the main loop until it returns true, `sink` is called exactly once per measurement.
+    pub fn poll_into<S>(&mut self, sink: &mut S) -> bool
+    where
+        S: SampleSink<Word, PinLed::Error, OneShotReader::Error>,
+    {
+        match self.read_nb() {
+            Err(nb::Error::WouldBlock) => false,
+            Err(nb::Error::Other(error)) => {
+                sink.on_sample(Err(error));
+                true
+            }
+            Ok(word) => {
+                sink.on_sample(Ok(word));
+                true
+            }
+        }
+    }
+
+    /// Powers the ADC up after `power_down`, see `adc::PowerControl`.
+    pub fn power_up(&mut self)
+    where
+        OneShotReader: PowerControl,
+    {
+        self.one_shot_reader.power_up();
+    }
+
+    /// Turns the LED off and powers the ADC down until the next `power_up`, see `adc::PowerControl`.
+    ///
+    /// The ADC is powered down even if turning the LED off fails, in which case `Error::LedError` is returned.
+    /// Any measurement started with `start_measurement` is abandoned.
+    pub fn power_down(&mut self) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>>
+    where
+        OneShotReader: PowerControl,
+    {
+        self.measurement_started = false;
+        let result = self.led_off().map_err(Error::LedError);
+        self.one_shot_reader.power_down();
+        result
+    }
+
+    /// Powers the ADC up, takes a reading like `read` and powers the ADC down again.
+    ///
+    /// The ADC is powered down even if the reading fails. The error types are the same as for `read`.
+    pub fn read_powered(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        OneShotReader: PowerControl,
+    {
+        self.one_shot_reader.power_up();
+        let result = self.read();
+        self.one_shot_reader.power_down();
+        result
+    }
+
+    /// Pushes readings into `vec` until it is full, returning the number of readings pushed.
+    ///
+    /// Readings already in `vec` are kept, so a full `vec` returns 0 without taking a reading. The first
+    /// error encountered is returned, the readings pushed before it are left in `vec`.
+    #[cfg(feature = "heapless")]
+    pub fn read_into_vec<const N: usize>(&mut self, vec: &mut heapless::Vec<Word, N>) -> core::result::Result<usize, Error<PinLed::Error, OneShotReader::Error>> {
+        let mut pushed = 0;
+        while !vec.is_full() {
+            let word = self.read()?;
+            let _ = vec.push(word);
+            pushed += 1;
+        }
+        Ok(pushed)
+    }
+
+    /// Takes `N` consecutive readings and returns their arithmetic mean.
+    ///
+    /// The readings are buffered on the stack in a `MovingAverage<N>`. The first error encountered
+    /// is returned and the remaining readings are not taken.
+    pub fn read_averaged<const N: usize>(&mut self) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let mut average: MovingAverage<N> = MovingAverage::new();
+        let mut mean = 0.0;
+        for _ in 0..N {
+            let raw: u32 = self.read()?.into();
+            mean = average.push(raw as f32);
+        }
+        Ok(mean)
+    }
+
+    /// Takes a reading and pushes it into `window`, returning the weighted average of the window.
+    ///
+    /// If the reading fails its error is returned and `window` is left untouched.
+    pub fn read_weighted<const N: usize>(&mut self, window: &mut WeightedWindow<N>) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw: u32 = self.read()?.into();
+        Ok(window.push(raw as f32))
+    }
+
+    /// Takes a reading and feeds it through `ema`, returning the smoothed value.
+    ///
+    /// The first reading seeds `ema` directly. If the reading fails its error is returned and `ema` is left untouched.
+    pub fn read_ema(&mut self, ema: &mut ExponentialMovingAverage) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw: u32 = self.read()?.into();
+        Ok(ema.push(raw as f32))
+    }
+
+    /// Reads the voltage like `read_voltage` and feeds it through `low_pass`, returning the filtered voltage.
+    ///
+    /// If the reading fails its error is returned and `low_pass` is left untouched.
+    pub fn read_lowpass(&mut self, low_pass: &mut IirLowPass) -> core::result::Result<Millivolts, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        Ok(Millivolts(low_pass.filter(self.read_voltage()?.0)))
+    }
+
+    /// Reads the voltage like `read_voltage` and feeds it through `hampel`, returning the despiked voltage.
+    ///
+    /// If the reading fails its error is returned and `hampel` is left untouched.
+    pub fn read_despiked<const N: usize>(&mut self, hampel: &mut HampelFilter<N>) -> core::result::Result<Millivolts, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        Ok(Millivolts(hampel.filter(self.read_voltage()?.0)))
+    }
+
+    /// Takes a burst of `N` consecutive readings and returns their mean weighted by `shape`.
+    ///
+    /// A tapered shape suppresses transients at the start and end of the burst, at the cost of slightly
+    /// more white noise than `read_averaged`, see `WindowShape`. The readings are buffered on the stack.
+    /// The first error encountered is returned and the remaining readings are not taken.
+    pub fn read_windowed<const N: usize>(&mut self, shape: WindowShape) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        const { assert!(N > 0, "read_windowed needs at least one reading") };
+        let mut samples = [0.0; N];
+        for sample in samples.iter_mut() {
+            let raw: u32 = self.read()?.into();
+            *sample = raw as f32;
+        }
+        Ok(shape.mean(&samples))
+    }
+
+    /// Takes `N` consecutive readings and returns the mean of the readings left after dropping the `trim` lowest and `trim` highest.
+    ///
+    /// This rejects outliers like `read_median` while averaging the remaining noise like `read_averaged`.
+    /// The readings are sorted in place on the stack. The first error encountered is returned and the
+    /// remaining readings are not taken.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `2 * trim` is not below `N`, since no reading would be left to average.
+    pub fn read_trimmed_mean<const N: usize>(&mut self, trim: usize) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        assert!(trim.saturating_mul(2) < N, "read_trimmed_mean must keep at least one reading");
+        let mut samples = [0u32; N];
+        for sample in samples.iter_mut() {
+            *sample = self.read()?.into();
+        }
+        samples.sort_unstable();
+        let kept = &samples[trim..N - trim];
+        let sum: u64 = kept.iter().map(|&raw| raw as u64).sum();
+        Ok(sum as f32 / kept.len() as f32)
+    }
+
+    /// Takes `N` consecutive readings and returns their signal-to-noise ratio, the mean divided by the sample standard deviation.
+    ///
+    /// The readings are accumulated in a `Stats`. Returns `f32::INFINITY` if the readings do not vary at all,
+    /// which includes `N` being 1. The first error encountered is returned and the remaining readings are not taken.
+    pub fn read_snr<const N: usize>(&mut self) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        const { assert!(N > 0, "read_snr needs at least one reading") };
+        let mut stats = Stats::new();
+        for _ in 0..N {
+            self.read_into_stats(&mut stats)?;
+        }
+        let std_dev = stats.sample_std_dev();
+        if std_dev == 0.0 {
+            return Ok(f32::INFINITY);
+        }
+        Ok(stats.mean() / std_dev)
+    }
+
+    /// Takes `N` consecutive readings and returns their median.
+    ///
+    /// The readings are sorted in place on the stack. For an even `N` the lower of the two middle
+    /// readings is returned, so no arithmetic on `Word` is needed. The first error encountered
+    /// is returned and the remaining readings are not taken.
+    pub fn read_median<const N: usize>(&mut self) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Ord,
+    {
+        const { assert!(N > 0, "read_median needs at least one reading") };
+        let mut samples = [self.read()?; N];
+        for sample in samples.iter_mut().skip(1) {
+            *sample = self.read()?;
+        }
+        samples.sort_unstable();
+        Ok(samples[(N - 1) / 2])
+    }
+
+    /// Takes `N` consecutive readings and returns the reading at the percentile `p`, using the nearest-rank method.
+    ///
+    /// `p` is a fraction, so 0.9 returns the 90th percentile, 0.0 the lowest and 1.0 the highest reading.
+    /// The readings are sorted in place on the stack like for `read_median`. The first error encountered
+    /// is returned and the remaining readings are not taken.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `p` is not within `0.0..=1.0`. Release builds clamp it to that range.
+    pub fn read_percentile<const N: usize>(&mut self, p: f32) -> core::result::Result<Word, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Ord,
+    {
+        const { assert!(N > 0, "read_percentile needs at least one reading") };
+        debug_assert!((0.0..=1.0).contains(&p), "the percentile must be within 0.0..=1.0");
+        let rank = libm::ceilf(p.clamp(0.0, 1.0) * N as f32) as usize;
+        let mut samples = [self.read()?; N];
+        for sample in samples.iter_mut().skip(1) {
+            *sample = self.read()?;
+        }
+        samples.sort_unstable();
+        Ok(samples[rank.clamp(1, N) - 1])
+    }
+
+    /// Takes `N` consecutive readings and returns their minimum, maximum and mean.
+    ///
+    /// The statistics are accumulated on the fly, so the readings are not buffered. The first
+    /// error encountered is returned and the remaining readings are not taken, so on success
+    /// `count` is always `N`.
+    pub fn read_stats<const N: usize>(&mut self) -> core::result::Result<SampleStats<Word>, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Ord,
+    {
+        const { assert!(N > 0, "read_stats needs at least one reading") };
+        let first = self.read()?;
+        let mut stats = SampleStats { min: first, max: first, mean: 0.0, count: 1 };
+        let mut sum: u64 = first.into() as u64;
+        for _ in 1..N {
+            let sample = self.read()?;
+            stats.min = stats.min.min(sample);
+            stats.max = stats.max.max(sample);
+            stats.count += 1;
+            sum += sample.into() as u64;
+        }
+        stats.mean = sum as f32 / stats.count as f32;
+        Ok(stats)
+    }
+
+    /// Reads the sensor output once and adds the raw reading to `stats`.
+    ///
+    /// On error `stats` is left untouched. The error types are the same as for `read`.
+    pub fn read_into_stats(&mut self, stats: &mut Stats) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let sample: u32 = self.read()?.into();
+        stats.push(sample as f32);
+        Ok(())
+    }
+
+    /// Reads the sensor output once and records the raw reading in `histogram`.
+    ///
+    /// On error `histogram` is left untouched. The error types are the same as for `read`.
+    pub fn read_into_histogram<const BINS: usize>(&mut self, histogram: &mut Histogram<BINS>) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let sample: u32 = self.read()?.into();
+        histogram.record(sample as f32);
+        Ok(())
+    }
+
+    /// Oversamples the output to gain `BITS` extra bits of resolution.
+    ///
+    /// Takes `4^BITS` consecutive readings, sums them and shifts the sum right by `BITS`, so the result has
+    /// `BITS` more bits than a single reading. The sum is accumulated in a `u64` on the stack rather than
+    /// buffering the readings, which cannot overflow since `BITS` is limited to 8 at compile time. A result
+    /// exceeding `u32` for very wide words saturates at `u32::MAX`.
+    ///
+    /// The first error encountered is returned and the remaining readings are not taken.
+    pub fn read_oversampled<const BITS: u8>(&mut self) -> core::result::Result<u32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        const { assert!(BITS <= 8, "read_oversampled supports at most 8 extra bits") };
+        let mut sum: u64 = 0;
+        for _ in 0..(1u32 << (2 * BITS)) {
+            let sample: u32 = self.read()?.into();
+            sum += sample as u64;
+        }
+        Ok((sum >> BITS).min(u32::MAX as u64) as u32)
+    }
+
+    /// Reads the sensor output voltage.
+    ///
+    /// The raw reading is scaled so that `2^resolution_bits` corresponds to the ADC reference voltage,
+    /// for example a raw reading of 2048 from a 12 bit ADC with a 3300mV reference yields 1650.0mV.
+    ///
+    /// The error types are the same as for `read`.
+    pub fn read_voltage(&mut self) -> core::result::Result<Millivolts, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw: u32 = self.read()?.into();
+        Ok(self.voltage_from(raw))
+    }
+
+    /// Reads the voltage once per cycle period for `duration_ms` and returns the mean voltage.
+    ///
+    /// The pulses are paced like `read_periodic`, so `duration_ms * 1000 / cycle_period_us` readings are taken,
+    /// always at least one. As they are evenly spaced, their mean is the time-weighted average over the window.
+    /// The density of the mean voltage, from `calibration().density`, is the mean density as long as no reading
+    /// is below the no-dust voltage.
+    ///
+    /// The first error aborts the window and is returned. The error types are the same as for `read_voltage`.
+    pub fn read_integrated(&mut self, duration_ms: u32) -> core::result::Result<Millivolts, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let cycle_period_us = u64::from(self.timing.cycle_period_us.max(1));
+        let count = (u64::from(duration_ms) * 1000 / cycle_period_us).max(1);
+        let mut sum = Millivolts(0.0);
+        for _ in 0..count {
+            self.wait_for_cycle();
+            let raw: u32 = self.read()?.into();
+            sum = sum + self.voltage_from(raw);
+        }
+        Ok(sum / count as f32)
+    }
+
+    /// Takes a reading like `read`, returning `Error::Saturated` for readings within the saturation margin.
+    fn read_unsaturated(&mut self) -> core::result::Result<u32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw: u32 = self.read()?.into();
+        self.check_saturation(raw)?;
+        Ok(raw)
+    }
+
+    fn check_saturation(&self, raw: u32) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>> {
+        let highest_code = (1u64 << self.resolution_bits) - 1;
+        let raw = if self.invert_data { highest_code - (raw as u64).min(highest_code) } else { raw as u64 };
+        if raw >= highest_code.saturating_sub(self.saturation_margin as u64) {
+            return Err(Error::Saturated);
+        }
+        Ok(())
+    }
+
+    /// Reads the sensor output and converts it to millivolts like `read_voltage`, using `f64` math.
+    ///
+    /// This is meant for targets where the extra precision is worth the cost of `f64`. The configuration
+    /// stays `f32` and is widened before the conversion.
+    pub fn read_voltage_f64(&mut self) -> core::result::Result<f64, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw: u32 = self.read()?.into();
+        Ok(self.voltage_from_f64(raw))
+    }
+
+    /// Reads the sensor output in millivolts using integer math only, see `fixed::voltage_mv`.
+    ///
+    /// The divider ratio is applied rounded to thousandths, which is exact for usual resistor ratios such as 1.5.
+    /// The error types are the same as for `read`.
+    #[cfg(feature = "fixed-point")]
+    pub fn read_voltage_mv_i32(&mut self) -> core::result::Result<i32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw: u32 = self.read()?.into();
+        Ok(self.voltage_mv_fixed(raw))
+    }
+
+    /// Reads the dust density in ng/m³ using integer math only, see `fixed::DENSITY_SCALE`.
+    ///
+    /// The float `Calibration` of the sensor is not used, so no float conversion is needed. The divider ratio
+    /// is applied as for `read_voltage_mv_i32`.
+    /// The error types are the same as for `read`.
+    #[cfg(feature = "fixed-point")]
+    pub fn read_dust_density_ug_fixed(&mut self, calibration: &FixedCalibration) -> core::result::Result<i32, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw = self.read_unsaturated()?;
+        Ok(calibration.density(self.voltage_mv_fixed(raw)))
+    }
+
+    /// Reads the dust density, converting the voltage with `curve` instead of the configured `Calibration`.
+    ///
+    /// The error types are the same as for `read_voltage`.
+    pub fn read_dust_density_curve<const N: usize>(&mut self, curve: &CalibrationCurve<N>) -> core::result::Result<MicrogramsPerM3, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        self.read_dust_density_with(curve)
+    }
+
+    /// Reads the dust density, converting the voltage with `converter` instead of the configured `Calibration`.
+    ///
+    /// The LED pulse, averaging and saturation check are the same as for `read_dust_density`, only the final
+    /// transfer function is replaced, for example by a closure evaluating a bench-calibrated polynomial.
+    pub fn read_dust_density_with<C: Converter>(&mut self, converter: &C) -> core::result::Result<MicrogramsPerM3, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let raw = self.read_unsaturated()?;
+        Ok(converter.convert(self.voltage_from(raw)))
+    }
+
+    /// Checks the wiring by reading the output once with the LED off and once with it on.
+    ///
+    /// The first reading is taken with `read_dark`. The test passes if that reading is below the calibrated
+    /// no-dust voltage and the reading with the LED on is at least `SELF_TEST_MIN_RESPONSE_MV` higher. Errors of either reading are returned as for `read`.
+    pub fn self_test(&mut self) -> core::result::Result<DiagnosticResult, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        let dark = self.read_dark()?;
+        let dark_mv = self.voltage_from(dark.into());
+        let lit_mv = self.read_voltage()?;
+        let status = if lit_mv - dark_mv < Self::SELF_TEST_MIN_RESPONSE_MV {
+            DiagnosticStatus::NoLedResponse
+        } else if dark_mv >= self.calibration.v_oc_mv {
+            DiagnosticStatus::DarkTooHigh
+        } else {
+            DiagnosticStatus::Passed
+        };
+        Ok(DiagnosticResult { dark_mv, lit_mv, status })
+    }
+
+    /// Returns the calibration with the no-dust voltage averaged over `samples` readings, see `calibrate_zero`.
+    fn measure_zero(&mut self, samples: u16) -> core::result::Result<Calibration, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        if samples == 0 {
+            return Err(Error::InvalidCalibration(ConfigError::CalibrationOutOfRange));
+        }
+        let mut sum = Millivolts(0.0);
+        for _ in 0..samples {
+            sum = sum + self.read_voltage()?;
+        }
+        let calibration = Calibration { v_oc_mv: sum / samples as f32, ..self.calibration };
+        self.check_calibration(&calibration).map_err(Error::InvalidCalibration)?;
+        Ok(calibration)
+    }
+
+    /// Measures the noise floor as the sample standard deviation of `samples` voltage readings.
+    ///
+    /// The readings should be taken in clean air. The result is returned and stored, see `noise_floor_mv`.
+    /// If any of the readings fails its error is returned and the stored noise floor is left untouched.
+    /// Calling this with fewer than 2 `samples` returns 0.0 and does not store it.
+    pub fn measure_noise_floor(&mut self, samples: u16) -> core::result::Result<Millivolts, Error<PinLed::Error, OneShotReader::Error>>
+    {
+        if samples < 2 {
+            return Ok(Millivolts(0.0));
+        }
+        let mut stats = Stats::new();
+        for _ in 0..samples {
+            stats.push(self.read_voltage()?.0);
+        }
+        let noise_floor_mv = Millivolts(stats.sample_std_dev());
+        self.noise_floor_mv = Some(noise_floor_mv);
+        Ok(noise_floor_mv)
+    }
+
+    /// Calibrates the sensitivity by averaging `samples` readings taken at the known dust density `known_density`.
+    ///
+    /// The no-dust voltage is kept, so calibrate it first with `calibrate_zero`. The sensitivity is set to
+    /// `(average_voltage - v_oc_mv) / known_density`. Returns `Error::BelowBaseline` if the average voltage
+    /// is not above the no-dust voltage, and `Error::InvalidCalibration` if the resulting calibration is rejected
+    /// as by `set_calibration`. On any error the calibration is left untouched. Calling this with 0 `samples`
+    /// does nothing.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `known_density` is not positive.
+    pub fn calibrate_span(&mut self, known_density: MicrogramsPerM3, samples: u16) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>>
+    {
+        debug_assert!(known_density.0 > 0.0, "the reference density must be positive");
+        if samples == 0 {
+            return Ok(());
+        }
+        let mut sum = Millivolts(0.0);
+        for _ in 0..samples {
+            sum = sum + self.read_voltage()?;
+        }
+        let above_v_oc_mv = (sum / samples as f32 - self.calibration.v_oc_mv).0;
+        if above_v_oc_mv.is_nan() || above_v_oc_mv <= 0.0 {
+            return Err(Error::BelowBaseline);
+        }
+        let calibration = Calibration { sensitivity_mv_per_ug: above_v_oc_mv / known_density.0, ..self.calibration };
+        self.check_calibration(&calibration).map_err(Error::InvalidCalibration)?;
+        self.calibration = calibration;
+        Ok(())
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay, Calibrated>
+where 
+    PinLed: OutputPin, 
+    PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
+{
+    /// Creates the sensor with the default configuration, see `with_calibration`.
+    fn from_peripherals(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader, delay: Delay, calibration: Calibration) -> Self {
+        Self {
+            pin_led,
+            one_shot_reader,
+            pin_data,
+            delay,
+            timing: Timing::datasheet(),
+            reference_mv: Self::REFERENCE_MV.0 as u16,
+            resolution_bits: Self::default_resolution_bits(),
+            divider_ratio: 1.0,
+            #[cfg(feature = "fixed-point")]
+            divider_ratio_milli: 1000,
+            samples_per_pulse: 1,
+            discard_first: 0,
+            saturation_margin: 0,
+            calibration,
+            led_polarity: LedPolarity::ActiveLow,
+            temperature_coefficient_mv_per_c: Self::TEMPERATURE_COEFFICIENT_MV_PER_C,
+            invert_data: false,
+            pre_sample: None,
+            last_raw: None,
+            noise_floor_mv: None,
+            measurement_started: false,
+            retry_read_errors: false,
+            pulsed: false,
+            converting: false,
+            converted: 0,
+            pulse_sum: 0,
+            _unused: PhantomData,
+            _state: PhantomData,
+        }
+    }
+
+    /// Replaces the calibration used to convert voltages to dust density.
+    ///
+    /// Returns `ConfigError::CalibrationOutOfRange` and keeps the current calibration if `calibration` is rejected
+    /// by `Calibration::validate`, or if its no-dust voltage is above the highest output voltage the ADC can
+    /// measure, which is the reference voltage times the divider ratio.
+    pub fn set_calibration(&mut self, calibration: Calibration) -> core::result::Result<(), ConfigError> {
+        self.check_calibration(&calibration)?;
+        self.calibration = calibration;
+        Ok(())
+    }
+
+    /// Returns the noise floor converted to a density with the configured sensitivity, or `None` if it was never measured.
+    ///
+    /// A multiple of it makes a deadband adapted to this unit, for example `Threshold::around(setpoint, 3.0 * noise.0)`
+    /// or `Deadband::new(3.0 * noise.0)`.
+    pub fn noise_floor_ug_m3(&self) -> Option<MicrogramsPerM3> {
+        self.noise_floor_mv.map(|noise_floor_mv| MicrogramsPerM3(noise_floor_mv.0 / self.calibration.sensitivity_mv_per_ug))
+    }
+
+    /// Converts a raw reading taken outside of the driver to a dust density, like `voltage_from_raw`.
+    ///
+    /// The configured `Calibration` is applied as for `read_dust_density`. Saturated readings are not rejected.
+    pub fn density_from_raw(&self, raw: Word) -> MicrogramsPerM3
+    where
+        Word: Into<u32>,
+    {
+        self.calibration.density(self.voltage_from_raw(raw))
+    }
+
+    /// Returns `last_raw` converted like `read_all`, using the current configuration.
+    ///
+    /// This lets the voltage and density of the last measurement be read again without pulsing the LED.
+    pub fn last_reading(&self) -> Option<Reading<Word>>
+    where
+        Word: Into<u32>,
+    {
+        let raw = self.last_raw?;
+        let voltage_mv = self.voltage_from_raw(raw);
+        Some(Reading {
+            raw,
+            voltage_mv,
+            density_ug_m3: self.calibration.density(voltage_mv),
+        })
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay, Calibrated>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
+{
+    /// Creates a new instance of the Gp2y1014au dust sensor
+    /// 
+    /// # Arguments
+    ///
+    /// * `pin_led`  - The pin connected to the led for the sensor.
+    /// * `pin_data` - The pin connected to data/out on the sensor.
+    /// * `one_shot_reader` - A structure that implements "crate::adc::OneShot"
+    /// * `delay` - A delay of the selected embedded-hal version, see "crate::hal::DelayUs", used to time the LED pulse.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use stm32f4xx_hal::
+    ///     adc::{
+    ///       Adc,
+    ///       config::AdcConfig
+    ///     };
+    /// 
+    /// // ... 
+    /// let pc1_led = gpioc.pc1.into_push_pull_output();
+    /// let pc0_out = gpioc.pc0.into_analog();
+    /// let mut adc = Adc::adc1(board_peripherals.ADC1, true, AdcConfig::default());
+    /// let delay = Delay::new(core_peripherals.SYST, &clocks);
+    /// let mut reader = Gp2y1014au::new(pc1_led, pc0_out, adc, delay);
+    /// ```
+    pub fn new(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader, delay: Delay) -> Self {
+        Self::with_calibration(pin_led, pin_data, one_shot_reader, delay, Calibration::datasheet())
+    }
+
+    /// Creates a new instance of the Gp2y1014au dust sensor for a board whose ADC differs from the defaults.
+    ///
+    /// The timing and calibration are the datasheet defaults as for `new`, use `builder` for anything else.
+    ///
+    /// # Arguments
+    ///
+    /// * `pin_led`, `pin_data`, `one_shot_reader`, `delay` - Same as for `new`.
+    /// * `reference_mv` - The reference voltage of the ADC, see `set_reference_mv`.
+    /// * `resolution_bits` - The resolution of the ADC in bits, see `set_resolution_bits`.
+    ///
+    /// Returns the error of `set_reference_mv` or `set_resolution_bits` if either argument is rejected.
+    pub fn with_config(
+        pin_led: PinLed,
+        pin_data: PinData,
+        one_shot_reader: OneShotReader,
+        delay: Delay,
+        reference_mv: Millivolts,
+        resolution_bits: u8,
+    ) -> core::result::Result<Self, ConfigError> {
+        let mut sensor = Self::new(pin_led, pin_data, one_shot_reader, delay);
+        sensor.set_reference_mv(reference_mv)?;
+        sensor.set_resolution_bits(resolution_bits)?;
+        Ok(sensor)
+    }
+
+    /// Creates a new instance of the Gp2y1014au dust sensor tuned for a specific unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `pin_led`, `pin_data`, `one_shot_reader`, `delay` - Same as for `new`.
+    /// * `calibration` - The conversion parameters from voltage to dust density.
+    pub fn with_calibration(
+        pin_led: PinLed,
+        pin_data: PinData,
+        one_shot_reader: OneShotReader,
+        delay: Delay,
+        calibration: Calibration,
+    ) -> Self {
+        Self::from_peripherals(pin_led, pin_data, one_shot_reader, delay, calibration)
+    }
+
+    /// Creates a new instance for another model sharing the interface, such as the GP2Y1010AU0F.
+    ///
+    /// The timing and calibration are taken from `model`, see `Model::timing` and `Model::calibration`.
+    /// The other arguments are the same as for `new`.
+    pub fn with_model(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader, delay: Delay, model: Model) -> Self {
+        let mut sensor = Self::with_calibration(pin_led, pin_data, one_shot_reader, delay, model.calibration());
+        sensor.timing = model.timing();
+        sensor
+    }
+
+    /// Creates a builder to configure the sensor before constructing it.
+    ///
+    /// The arguments are the same as for `new`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut reader = Gp2y1014au::builder(pc1_led, pc0_out, adc, delay)
+    ///     .reference_mv(Millivolts(3000.0))
+    ///     .adc_bits(10)
+    ///     .build()?;
+    /// ```
+    pub fn builder(
+        pin_led: PinLed,
+        pin_data: PinData,
+        one_shot_reader: OneShotReader,
+        delay: Delay,
+    ) -> Gp2y1014auBuilder<PinLed, OneShotReader, Adc, Word, PinData, Delay> {
+        Gp2y1014auBuilder {
+            pin_led,
+            one_shot_reader,
+            pin_data,
+            delay,
+            timing: Timing::datasheet(),
+            reference_mv: Self::REFERENCE_MV,
+            resolution_bits: Self::default_resolution_bits(),
+            divider_ratio: 1.0,
+            samples_per_pulse: 1,
+            discard_first: 0,
+            saturation_margin: 0,
+            calibration: Calibration::datasheet(),
+            led_polarity: LedPolarity::ActiveLow,
+            temperature_coefficient_mv_per_c: Self::TEMPERATURE_COEFFICIENT_MV_PER_C,
+            invert_data: false,
+            pre_sample: None,
+            _unused: PhantomData,
+        }
+    }
+
+    /// Calibrates the no-dust voltage by averaging `samples` readings taken in known-clean air.
+    ///
+    /// If any of the readings fails, or the measured calibration is rejected as by `set_calibration`, the error is
+    /// returned and the calibration is left untouched. Calling this with 0 `samples` returns `Error::InvalidCalibration`.
+    pub fn calibrate_zero(&mut self, samples: u16) -> core::result::Result<(), Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        self.calibration = self.measure_zero(samples)?;
+        Ok(())
+    }
+
+    /// Reads the dust density.
+    ///
+    /// The voltage is converted using the configured `Calibration`, readings below the no-dust
+    /// voltage are clamped to 0.
+    ///
+    /// The error types are the same as for `read_voltage`, plus `Error::Saturated` if the reading is
+    /// within the saturation margin of the full scale, see `set_saturation_margin`.
+    pub fn read_dust_density(&mut self) -> core::result::Result<MicrogramsPerM3, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        let raw = self.read_unsaturated()?;
+        Ok(self.calibration.density(self.voltage_from(raw)))
+    }
+
+    /// Reads the dust density like `read_dust_density`, converted to `unit`.
+    ///
+    /// The density is computed in µg/m³ and only converted at the end, so both units have the same precision.
+    pub fn read_dust_density_in(&mut self, unit: DensityUnit) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(unit.from_ug_m3(self.read_dust_density()?))
+    }
+
+    /// Reads the dust density in µg/m³ like `read_dust_density`, using `f64` math, see `read_voltage_f64`.
+    pub fn read_dust_density_f64(&mut self) -> core::result::Result<f64, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        let raw = self.read_unsaturated()?;
+        Ok(self.calibration.density_f64(self.voltage_from_f64(raw)))
+    }
+
+    /// Reads the dust density, compensating the no-dust voltage for the temperature.
+    ///
+    /// The calibrated no-dust voltage is adjusted by `temperature_coefficient_mv_per_c` for every °C
+    /// that `temp_c` is above `REFERENCE_TEMPERATURE_C`. A positive coefficient means the no-dust voltage
+    /// rises with the temperature. The temperature has to be measured by a separate sensor.
+    ///
+    /// Readings below the compensated no-dust voltage are clamped to 0. The error types are the same as for `read_voltage`.
+    pub fn read_dust_density_compensated(&mut self, temp_c: f32) -> core::result::Result<MicrogramsPerM3, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        let raw = self.read_unsaturated()?;
+        let voltage = self.voltage_from(raw);
+        let mut calibration = self.calibration;
+        calibration.v_oc_mv = calibration.v_oc_mv + Millivolts(self.temperature_coefficient_mv_per_c * (temp_c - Self::REFERENCE_TEMPERATURE_C));
+        Ok(calibration.density(voltage))
+    }
+
+    /// Reads the dust density like `read_dust_density` and updates `threshold` with it.
+    ///
+    /// Returns the crossing if the alert state changed. On error `threshold` is left untouched.
+    pub fn read_threshold(&mut self, threshold: &mut Threshold) -> core::result::Result<Option<Crossing>, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(threshold.update(self.read_dust_density()?.0))
+    }
+
+    /// Reads the dust density like `read_dust_density` and feeds it through `kalman`, returning the filtered density.
+    ///
+    /// If the reading fails its error is returned and `kalman` is left untouched.
+    pub fn read_filtered(&mut self, kalman: &mut Kalman1D) -> core::result::Result<MicrogramsPerM3, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(MicrogramsPerM3(kalman.update(self.read_dust_density()?.0)))
+    }
+
+    /// Reads the dust density like `read_dust_density` and passes it through `deadband`, returning the value to display.
+    ///
+    /// If the reading fails its error is returned and `deadband` is left untouched.
+    pub fn read_quantized(&mut self, deadband: &mut Deadband) -> core::result::Result<MicrogramsPerM3, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(MicrogramsPerM3(deadband.apply(self.read_dust_density()?.0)))
+    }
+
+    /// Reads the dust density and converts it to the US EPA air quality index, see `conversion::aqi_from_density`.
+    ///
+    /// The error types are the same as for `read_dust_density`.
+    pub fn read_aqi(&mut self) -> core::result::Result<u16, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(aqi_from_density(self.read_dust_density()?))
+    }
+
+    /// Reads the dust density and maps it to a score from 100 for clean to 0 for unhealthy air, see `conversion::quality_score`.
+    ///
+    /// The error types are the same as for `read_dust_density`.
+    pub fn read_quality_score(&mut self) -> core::result::Result<u8, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(quality_score(self.read_dust_density()?))
+    }
+
+    /// Reads the dust density and estimates the particle count per 0.01 cubic foot, see `conversion::particles_per_0_01cf`.
+    ///
+    /// The error types are the same as for `read_dust_density`.
+    pub fn read_particle_count(&mut self) -> core::result::Result<f32, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(particles_per_0_01cf(self.read_dust_density()?))
+    }
+
+    /// Takes a single reading and returns it as raw reading, voltage and dust density.
+    ///
+    /// The LED is only pulsed once, so all of the representations belong to the same measurement.
+    /// The conversions are the same as for `read_voltage` and `read_dust_density`, including averaging
+    /// `samples_per_pulse` conversions and returning `Error::Saturated` for saturated readings.
+    pub fn read_all(&mut self) -> core::result::Result<Reading<Word>, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        let raw = self.read()?;
+        self.check_saturation(raw.into())?;
+        let voltage_mv = self.voltage_from_raw(raw);
+        Ok(Reading {
+            raw,
+            voltage_mv,
+            density_ug_m3: self.calibration.density(voltage_mv),
+        })
+    }
+
+    /// Takes a single reading like `read_all` and converts it to `T`, one of the `reading` newtypes.
+    ///
+    /// The error types are the same as for `read_all`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let voltage: Millivolts = sensor.read_as()?;
+    /// let Aqi(aqi) = sensor.read_as()?;
+    /// ```
+    pub fn read_as<T: FromReading<Word>>(&mut self) -> core::result::Result<T, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        Ok(T::from_reading(&self.read_all()?))
+    }
+
+    /// Takes a complete measurement using the stored configuration, this is the recommended entry point.
+    ///
+    /// Waits for the cycle period like `read_periodic`, pulses the LED once while averaging
+    /// `samples_per_pulse` conversions, and converts the result like `read_all`.
+    ///
+    /// The error types are the same as for `read_dust_density`.
+    pub fn measure(&mut self) -> core::result::Result<Reading<Word>, Error<PinLed::Error, OneShotReader::Error>>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        self.wait_for_cycle();
+        self.read_all()
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay, Uncalibrated>
+where 
+    PinLed: OutputPin, 
+    PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
+{
+    /// Replaces the calibration and enables the density methods.
+    ///
+    /// If `calibration` is rejected as by `set_calibration`, the still uncalibrated sensor is handed back
+    /// together with `ConfigError::CalibrationOutOfRange`.
+    #[allow(clippy::type_complexity)]
+    pub fn calibrate(mut self, calibration: Calibration) -> core::result::Result<Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay, Calibrated>, (Self, ConfigError)> {
+        if let Err(error) = self.check_calibration(&calibration) {
+            return Err((self, error));
+        }
+        self.calibration = calibration;
+        Ok(self.into_state())
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, Delay, Uncalibrated>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
+{
+    /// Creates a new instance of the Gp2y1014au dust sensor which has to be calibrated before reading densities.
+    ///
+    /// The arguments are the same as for `new`. Only the raw and voltage readings are available until the sensor
+    /// is calibrated with `calibrate` or `calibrate_zero`, which return it in the `Calibrated` state.
+    pub fn new_uncalibrated(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader, delay: Delay) -> Self {
+        Gp2y1014au::new(pin_led, pin_data, one_shot_reader, delay).into_state()
+    }
+
+    /// Calibrates the no-dust voltage by averaging `samples` readings taken in known-clean air, and enables the
+    /// density methods.
+    ///
+    /// The sensitivity is kept. If any of the readings fails, or the measured calibration is rejected as by
+    /// `set_calibration`, the still uncalibrated sensor is handed back together with the error. Calling this with
+    /// 0 `samples` returns `Error::InvalidCalibration`, as on a calibrated sensor.
+    #[allow(clippy::type_complexity)]
+    pub fn calibrate_zero(mut self, samples: u16) -> core::result::Result<Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay, Calibrated>, (Self, Error<PinLed::Error, OneShotReader::Error>)>
+    where
+        Word: Into<u32> + TryFrom<u32>,
+    {
+        match self.measure_zero(samples) {
+            Ok(calibration) => {
+                self.calibration = calibration;
+                Ok(self.into_state())
+            }
+            Err(error) => Err((self, error)),
+        }
+    }
+}
+
+impl <PinLed, Adc, Word, PinData, Delay>  Gp2y1014au <PinLed, SharedAdc, Adc, Word, PinData, Delay>
+where 
+    PinLed: OutputPin, 
+    PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
+{
+    /// Creates a new instance of the Gp2y1014au dust sensor which does not own an ADC.
+    ///
+    /// Take readings with `read_with`, passing the shared ADC each time. The methods which use the reader
+    /// owned by the sensor, like `read`, are not available since `SharedAdc` does not implement `OneShot`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut first = Gp2y1014au::new_shared(pc1_led, pc0_out, delay_1);
+    /// let mut second = Gp2y1014au::new_shared(pc3_led, pc2_out, delay_2);
+    /// let first_reading = first.read_with(&mut adc);
+    /// let second_reading = second.read_with(&mut adc);
+    /// ```
+    pub fn new_shared(pin_led: PinLed, pin_data: PinData, delay: Delay) -> Self {
+        Self::from_peripherals(pin_led, pin_data, SharedAdc, delay, Calibration::datasheet())
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData>  Gp2y1014au <PinLed, OneShotReader, Adc, Word, PinData, NoDelay>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc> ,
+    Word: Copy,
+{
+    /// Creates a new instance of the Gp2y1014au dust sensor which does not wait during the LED pulse.
+    ///
+    /// This is meant for HALs without a microsecond delay source. The caller must ensure that the output is
+    /// sampled 280µs after the LED is enabled, otherwise the readings do not match the datasheet.
+    pub fn new_without_delay(pin_led: PinLed, pin_data: PinData, one_shot_reader: OneShotReader) -> Self {
+        Self::new(pin_led, pin_data, one_shot_reader, NoDelay)
+    }
+}
+
+/// Checks a reference voltage passed to `Gp2y1014au::set_reference_mv` and rounds it to whole millivolts.
+fn check_reference_mv(reference_mv: Millivolts) -> core::result::Result<u16, ConfigError> {
+    let rounded_mv = libm::roundf(reference_mv.0);
+    if rounded_mv.is_nan() || rounded_mv < 1.0 || rounded_mv > u16::MAX as f32 {
+        return Err(ConfigError::ReferenceOutOfRange);
+    }
+    Ok(rounded_mv as u16)
+}
+
+/// Checks a divider ratio passed to `Gp2y1014au::set_divider_ratio`.
+fn check_divider_ratio(divider_ratio: f32) -> core::result::Result<(), ConfigError> {
+    if !divider_ratio.is_finite() || divider_ratio < 1.0 {
+        return Err(ConfigError::DividerOutOfRange);
+    }
+    Ok(())
+}
+
+/// Runs a conversion on `pin`, waiting while it would block.
+fn sample<R, Adc, Word, Pin>(reader: &mut R, pin: &mut Pin) -> core::result::Result<Word, R::Error>
+where
+    R: OneShot<Adc, Word, Pin>,
+    Pin: Channel<Adc>,
+{
+    sample_yielding(reader, pin, || ())
+}
+
+/// Runs a conversion on `pin` like `sample`, calling `on_block` every time it would block.
+fn sample_yielding<R, Adc, Word, Pin, F>(reader: &mut R, pin: &mut Pin, mut on_block: F) -> core::result::Result<Word, R::Error>
+where
+    R: OneShot<Adc, Word, Pin>,
+    Pin: Channel<Adc>,
+    F: FnMut(),
+{
+    loop { 
+        let read_result = reader.read(pin);
+
+        match read_result {
+            Ok(word) => return Ok(word),
+            Err(nb::Error::Other(failed)) => return Err(failed),
+            Err(nb::Error::WouldBlock) => on_block(),
+        };
+    }
+}
+
+/// Averages `count` conversions taken with `convert`, rounded to the nearest code, see `average_word`.
+///
+/// At least one conversion is taken. The first error is returned and the remaining conversions are not taken.
+fn average_conversions<Word, E>(count: u8, mut convert: impl FnMut() -> core::result::Result<Word, E>) -> core::result::Result<Word, E>
+where
+    Word: Copy + Into<u32> + TryFrom<u32>,
+{
+    let count = count.max(1);
+    let mut sum = 0;
+    let mut word = convert()?;
+    for _ in 1..count {
+        sum += u64::from(word.into());
+        word = convert()?;
+    }
+    sum += u64::from(word.into());
+    Ok(average_word(sum, count, word))
+}
+
+/// Returns `sum / count` rounded to the nearest code, where `sum` adds up `count` conversions ending with `last`.
+///
+/// The average of words always fits in a word, `last` is only returned if `Word::try_from` fails regardless.
+fn average_word<Word: TryFrom<u32>>(sum: u64, count: u8, last: Word) -> Word {
+    let count = u64::from(count.max(1));
+    let average = (sum + count / 2) / count;
+    u32::try_from(average).ok().and_then(|average| Word::try_from(average).ok()).unwrap_or(last)
+}
+
+/// Polls the conversion like `sample`, returning `nb::Error::WouldBlock` once it blocked `max_polls` times in a row.
+fn sample_bounded<R, Adc, Word, Pin>(reader: &mut R, pin: &mut Pin, max_polls: u32) -> nb::Result<Word, R::Error>
+where
+    R: OneShot<Adc, Word, Pin>,
+    Pin: Channel<Adc>,
+{
+    let mut polls = 0;
+    loop {
+        match reader.read(pin) {
+            Err(nb::Error::WouldBlock) if polls < max_polls => polls += 1,
+            result => return result,
+        }
+    }
+}
+
+impl <PinLed, OneShotReader, Adc, Word, PinData, Delay>  Gp2y1014auBuilder <PinLed, OneShotReader, Adc, Word, PinData, Delay>
+where 
+    PinLed: OutputPin, 
+    OneShotReader: OneShot<Adc, Word, PinData>,
+    PinData: Channel<Adc> ,
+    Word: Copy,
+    Delay: DelayUs,
+{
+    /// Sets the timing of the LED pulse, see `Gp2y1014au::set_timing`.
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Sets the time between enabling the LED and sampling the output, see `Timing::sample_delay_us`.
+    pub fn sampling_delay_us(mut self, sampling_delay_us: u16) -> Self {
+        self.timing.sample_delay_us = sampling_delay_us;
+        self
+    }
+
+    /// Sets the total time the LED is enabled for each reading, see `Timing::pulse_width_us`.
+    ///
+    /// The pulse must end after the output is sampled, which `build` checks.
+    pub fn pulse_width_us(mut self, pulse_width_us: u16) -> Self {
+        self.timing.pulse_width_us = pulse_width_us;
+        self
+    }
+
+    /// Sets the reference voltage of the ADC, see `Gp2y1014au::REFERENCE_MV`.
+    ///
+    /// The reference voltage is checked and rounded by `build` as by `Gp2y1014au::set_reference_mv`.
+    pub fn reference_mv(mut self, reference_mv: Millivolts) -> Self {
+        self.reference_mv = reference_mv;
+        self
+    }
+
+    /// Sets the resolution of the ADC in bits, see `Gp2y1014au::RESOLUTION_BITS`.
+    ///
+    /// The resolution is checked by `build` as by `Gp2y1014au::set_resolution_bits`.
+    pub fn adc_bits(mut self, resolution_bits: u8) -> Self {
+        self.resolution_bits = resolution_bits;
+        self
+    }
+
+    /// Sets the ratio of the voltage divider in front of the ADC, see `Gp2y1014au::set_divider_ratio`.
+    ///
+    /// The ratio is checked by `build` as by `Gp2y1014au::set_divider_ratio`.
+    pub fn divider_ratio(mut self, divider_ratio: f32) -> Self {
+        self.divider_ratio = divider_ratio;
+        self
+    }
+
+    /// Sets the number of conversions averaged within each LED pulse, see `Gp2y1014au::set_samples_per_pulse`.
+    ///
+    /// The number is checked by `build` as by `Gp2y1014au::set_samples_per_pulse`.
+    pub fn samples_per_pulse(mut self, samples_per_pulse: u8) -> Self {
+        self.samples_per_pulse = samples_per_pulse;
+        self
+    }
+
+    /// Sets the number of conversions discarded at the start of each LED pulse, see `Gp2y1014au::set_discard_first`.
+    pub fn discard_first(mut self, discard_first: u8) -> Self {
+        self.discard_first = discard_first;
+        self
+    }
+
+    /// Sets the number of codes below the full scale which are considered saturated, see `Gp2y1014au::set_saturation_margin`.
+    pub fn saturation_margin(mut self, saturation_margin: u32) -> Self {
+        self.saturation_margin = saturation_margin;
+        self
+    }
+
+    /// Sets a hook called right before every conversion, see `Gp2y1014au::set_pre_sample`.
+    pub fn pre_sample(mut self, pre_sample: fn(&mut OneShotReader, &mut PinData)) -> Self {
+        self.pre_sample = Some(pre_sample);
+        self
+    }
+
+    /// Sets whether the output is read through an inverting stage, see `Gp2y1014au::set_invert_data`.
+    pub fn invert_data(mut self, invert_data: bool) -> Self {
+        self.invert_data = invert_data;
+        self
+    }
+
+    /// Sets the level of the LED pin which turns the LED on, see `Gp2y1014au::set_led_polarity`.
+    pub fn led_polarity(mut self, led_polarity: LedPolarity) -> Self {
+        self.led_polarity = led_polarity;
+        self
+    }
+
+    /// Sets the change of the no-dust voltage in millivolts per °C, see `Gp2y1014au::read_dust_density_compensated`.
+    pub fn temperature_coefficient_mv_per_c(mut self, temperature_coefficient_mv_per_c: f32) -> Self {
+        self.temperature_coefficient_mv_per_c = temperature_coefficient_mv_per_c;
+        self
+    }
+
+    /// Sets the timing and calibration to the defaults of `model`, see `Gp2y1014au::with_model`.
+    ///
+    /// Settings applied after this override the model defaults.
+    pub fn model(mut self, model: Model) -> Self {
+        self.timing = model.timing();
+        self.calibration = model.calibration();
+        self
+    }
+
+    /// Sets the conversion parameters from voltage to dust density, see `Calibration::datasheet`.
+    pub fn calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Creates the configured sensor.
+    ///
+    /// Returns `ConfigError::InvalidTiming` if the configured timing is rejected by `Timing::validate`, the error of
+    /// `Gp2y1014au::set_reference_mv`, `Gp2y1014au::set_resolution_bits`, `Gp2y1014au::set_divider_ratio` or
+    /// `Gp2y1014au::set_samples_per_pulse` if the corresponding setting is rejected, and `ConfigError::CalibrationOutOfRange` if the calibration is rejected as
+    /// by `Gp2y1014au::set_calibration`.
+    pub fn build(self) -> core::result::Result<Gp2y1014au<PinLed, OneShotReader, Adc, Word, PinData, Delay>, ConfigError> {
+        self.timing.validate()?;
+        let reference_mv = check_reference_mv(self.reference_mv)?;
+        check_divider_ratio(self.divider_ratio)?;
+        let mut sensor = Gp2y1014au::with_calibration(self.pin_led, self.pin_data, self.one_shot_reader, self.delay, self.calibration);
+        sensor.timing = self.timing;
+        sensor.reference_mv = reference_mv;
+        sensor.set_resolution_bits(self.resolution_bits)?;
+        sensor.divider_ratio = self.divider_ratio;
+        sensor.store_divider_ratio_milli();
+        sensor.set_samples_per_pulse(self.samples_per_pulse)?;
+        sensor.discard_first = self.discard_first;
+        sensor.saturation_margin = self.saturation_margin;
+        sensor.led_polarity = self.led_polarity;
+        sensor.temperature_coefficient_mv_per_c = self.temperature_coefficient_mv_per_c;
+        sensor.invert_data = self.invert_data;
+        sensor.pre_sample = self.pre_sample;
+        sensor.check_calibration(&sensor.calibration)?;
+        Ok(sensor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use embedded_hal::digital::{ErrorKind, ErrorType, OutputPin};
+    use embedded_hal::delay::DelayNs;
+    use crate::adc::OneShot;
+    use crate::adc::Channel;
+    use crate::adc::PowerControl;
+    use crate::units::{MicrogramsPerM3, Millivolts};
+    struct BadState;
+    struct GoodState;
+    struct TestAdc {
+        _garbage: bool 
+    }
+
+    impl TestAdc {
+        fn new() -> Self {
+            Self {_garbage: true}
+        }
+    }
+    struct TestAnalogPin<STATE> {
+        _unused: PhantomData<STATE>
+    }
+    impl <STATE> TestAnalogPin<STATE> {
+        fn new() -> Self {
+            Self { _unused: PhantomData }
+        }
+    }
+    
+    struct TestOutputPin<STATE> {
+        _unused: PhantomData<STATE>
+    }
+
+
+    impl <STATE> TestOutputPin<STATE> {
+        fn new() -> Self { 
+            Self { _unused: PhantomData }
+        }
+    }
+
+    impl <STATE> ErrorType for TestOutputPin<STATE> {
+        type Error = ErrorKind;
+    }
+
+    impl OutputPin for TestOutputPin<GoodState> {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+    impl OutputPin for TestOutputPin<BadState> {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            Err(ErrorKind::Other)
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            Err(ErrorKind::Other)
+        }
+    }
+    struct TestRecordingPin {
+        high: bool,
+        set_high_calls: usize,
+        set_low_calls: usize
+    }
+
+    impl TestRecordingPin {
+        fn new() -> Self {
+            Self { high: false, set_high_calls: 0, set_low_calls: 0 }
+        }
+    }
+
+    impl ErrorType for TestRecordingPin {
+        type Error = ErrorKind;
+    }
+
+    impl OutputPin for TestRecordingPin {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.high = true;
+            self.set_high_calls += 1;
+            Ok(())
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.high = false;
+            self.set_low_calls += 1;
+            Ok(())
+        }
+    }
+
+    struct TestFlakyPin {
+        failures: usize,
+        set_high_calls: usize
+    }
+
+    impl ErrorType for TestFlakyPin {
+        type Error = ErrorKind;
+    }
+
+    impl OutputPin for TestFlakyPin {
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set_high_calls += 1;
+            Ok(())
+        }
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(ErrorKind::Other);
+            }
+            Ok(())
+        }
+    }
+
+    /// Implements the embedded-hal 0.2 output pin trait on top of the 1.0 one, for the `eh02` bounds.
+    macro_rules! impl_eh02_output_pin {
+        ($($pin:ty),*) => {
+            $(#[cfg(feature = "eh02")]
+            impl embedded_hal_02::digital::v2::OutputPin for $pin {
+                type Error = ErrorKind;
+                fn set_high(&mut self) -> Result<(), Self::Error> {
+                    OutputPin::set_high(self)
+                }
+                fn set_low(&mut self) -> Result<(), Self::Error> {
+                    OutputPin::set_low(self)
+                }
+            })*
+        };
+    }
+
+    /// Implements the embedded-hal 0.2 delay trait on top of the 1.0 one, for the `eh02` bounds.
+    macro_rules! impl_eh02_delay {
+        ($($delay:ty),*) => {
+            $(#[cfg(feature = "eh02")]
+            impl embedded_hal_02::blocking::delay::DelayUs<u32> for $delay {
+                fn delay_us(&mut self, us: u32) {
+                    DelayNs::delay_us(self, us);
+                }
+            })*
+        };
+    }
+
+    impl_eh02_output_pin!(TestOutputPin<GoodState>, TestOutputPin<BadState>, TestRecordingPin, TestFlakyPin);
+
+    impl <STATE> Channel<TestAdc> for TestAnalogPin<STATE> {
+        type ID = u8;
+        fn channel() -> Self::ID {
+            1
+        }
+    }
+
+    impl OneShot<TestAdc, u8, TestAnalogPin<GoodState>> for TestAdc {
+        type Error = ();
+        fn read(&mut self, _: &mut TestAnalogPin<GoodState>) -> nb::Result<u8, ()> {
+            Ok(10u8)
+        }
+    }
+    impl OneShot<TestAdc, u8, TestAnalogPin<BadState>> for TestAdc {
+        type Error = ();
+        fn read(&mut self, _: &mut TestAnalogPin<BadState>) -> nb::Result<u8, ()> {
+            Err(nb::Error::Other(()))
+        }
+    }
+    struct TestSequenceAdc {
+        values: &'static [u16],
+        index: usize,
+        powered: bool
+    }
+
+    impl TestSequenceAdc {
+        fn new(values: &'static [u16]) -> Self {
+            Self { values, index: 0, powered: true }
+        }
+    }
+
+    impl PowerControl for TestSequenceAdc {
+        fn power_up(&mut self) {
+            self.powered = true;
+        }
+        fn power_down(&mut self) {
+            self.powered = false;
+        }
+    }
+
+    impl <STATE> Channel<TestSequenceAdc> for TestAnalogPin<STATE> {
+        type ID = u8;
+        fn channel() -> Self::ID {
+            1
+        }
+    }
+
+    impl OneShot<TestSequenceAdc, u16, TestAnalogPin<GoodState>> for TestSequenceAdc {
+        type Error = ();
+        fn read(&mut self, _: &mut TestAnalogPin<GoodState>) -> nb::Result<u16, ()> {
+            assert!(self.powered, "Expected the ADC to be powered up");
+            let value = self.values[self.index % self.values.len()];
+            self.index += 1;
+            Ok(value)
+        }
+    }
+
+    struct TestSlowAdc {
+        pending: usize
+    }
+
+    impl <STATE> Channel<TestSlowAdc> for TestAnalogPin<STATE> {
+        type ID = u8;
+        fn channel() -> Self::ID {
+            1
+        }
+    }
+
+    impl OneShot<TestSlowAdc, u16, TestAnalogPin<GoodState>> for TestSlowAdc {
+        type Error = ();
+        fn read(&mut self, _: &mut TestAnalogPin<GoodState>) -> nb::Result<u16, ()> {
+            if self.pending > 0 {
+                self.pending -= 1;
+                return Err(nb::Error::WouldBlock);
+            }
+            Ok(42)
+        }
+    }
+
+    struct TestDelay {
+        waited_ns: u32
+    }
+
+    impl TestDelay {
+        fn new() -> Self {
+            Self { waited_ns: 0 }
+        }
+    }
+
+    impl DelayNs for TestDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.waited_ns += ns;
+        }
+    }
+
+    impl_eh02_delay!(TestDelay);
+
+    #[test]
+    fn read_returns_value_when_no_errors_present() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(10u8, sensor.read().ok().unwrap() );
+    }
+
+    #[test]
+    fn read_returns_error_when_one_shot_read_fails() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.read().expect_err("Expected this function to error");
+    }
+
+    #[test]
+    fn read_errors_can_be_unwrapped() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(10u8, sensor.read().unwrap());
+    }
+
+    #[test]
+    fn read_errors_can_be_copied_and_compared() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let error = sensor.read().unwrap_err();
+        let copy = error;
+        assert_eq!(crate::sensor::Error::ReadError(()), error);
+        assert_eq!(copy, error);
+        assert_ne!(crate::sensor::Error::LedError(ErrorKind::Other), error);
+    }
+
+    #[test]
+    fn read_waits_for_the_full_led_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.read().ok().unwrap();
+        let (_, _, _, delay) = sensor.split();
+        assert_eq!(320_000, delay.waited_ns);
+    }
+
+    #[test]
+    fn read_turns_an_active_low_led_off_with_a_high_level() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        sensor.read().ok().unwrap();
+        let (led_pin, _, _, _) = sensor.split();
+        assert!(led_pin.high);
+        assert_eq!((1, 1), (led_pin.set_low_calls, led_pin.set_high_calls));
+    }
+
+    #[test]
+    fn read_turns_an_active_high_led_off_with_a_low_level() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::builder(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new())
+            .led_polarity(crate::sensor::LedPolarity::ActiveHigh)
+            .build().unwrap();
+        sensor.read().ok().unwrap();
+        let (led_pin, _, _, _) = sensor.split();
+        assert!(!led_pin.high);
+        assert_eq!((1, 1), (led_pin.set_low_calls, led_pin.set_high_calls));
+    }
+
+    #[test]
+    fn read_with_retries_retries_led_errors() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestFlakyPin { failures: 2, set_high_calls: 0 }, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok((10u8, 2)), sensor.read_with_retries(2));
+        sensor.pin_led_mut().failures = 3;
+        assert_eq!(Err(crate::sensor::Error::LedError(ErrorKind::Other)), sensor.read_with_retries(2));
+    }
+
+    #[test]
+    fn read_with_retries_only_retries_read_errors_when_enabled() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.read_with_retries(3).expect_err("Expected this function to error");
+        assert_eq!(280_000, sensor.delay().waited_ns);
+        sensor.set_retry_read_errors(true);
+        sensor.read_with_retries(3).expect_err("Expected this function to error");
+        assert_eq!(5 * 280_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_periodic_waits_for_the_rest_of_the_cycle() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.read_periodic().ok().unwrap();
+        assert_eq!(320_000, sensor.delay().waited_ns);
+        sensor.read_periodic().ok().unwrap();
+        assert_eq!(10_320_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_integrated_averages_one_reading_per_cycle() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 1200, 1100]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Ok(Millivolts(1100.0)), sensor.read_integrated(30));
+        assert_eq!(3, sensor.reader().index);
+        assert_eq!(2 * 9_680_000 + 3 * 320_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn measure_averages_one_pulse_per_cycle() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1099, 1101]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .reference_mv(Millivolts(4096.0))
+            .samples_per_pulse(2)
+            .build().unwrap();
+        let reading = sensor.measure().ok().unwrap();
+        assert_eq!(crate::reading::Reading { raw: 1100, voltage_mv: Millivolts(1100.0), density_ug_m3: MicrogramsPerM3(100.0) }, reading);
+        assert_eq!(320_000, sensor.delay().waited_ns);
+        sensor.measure().ok().unwrap();
+        assert_eq!(10_320_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_nb_returns_would_block_while_converting() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 2 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(nb::Error::WouldBlock), sensor.read_nb());
+        assert!(!sensor.pin_led().high);
+        assert_eq!(Err(nb::Error::WouldBlock), sensor.read_nb());
+        assert_eq!(Ok(42), sensor.read_nb());
+        assert!(sensor.pin_led().high);
+        assert_eq!(1, sensor.pin_led().set_low_calls);
+        assert_eq!(320_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn poll_into_passes_the_finished_measurement_to_the_sink() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 1 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        let mut samples = 0;
+        let mut last = None;
+        let mut sink = |result| {
+            samples += 1;
+            last = Some(result);
+        };
+        assert!(!sensor.poll_into(&mut sink));
+        assert!(sensor.poll_into(&mut sink));
+        assert_eq!((1, Some(Ok(42))), (samples, last));
+        assert!(sensor.pin_led().high);
+    }
+
+    #[test]
+    fn reset_clears_the_state_between_readings() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 1 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok(42), nb::block!(sensor.read_nb()));
+        sensor.read_periodic().ok().unwrap();
+        sensor.reader_mut().pending = 1;
+        assert_eq!(Err(nb::Error::WouldBlock), sensor.read_nb());
+        sensor.reset();
+        assert!(sensor.pin_led().high);
+        assert_eq!(None, sensor.last_raw());
+        let waited_ns = sensor.delay().waited_ns;
+        sensor.read_periodic().ok().unwrap();
+        assert_eq!(waited_ns + 320_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn split_turns_the_led_off_during_read_nb() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 2 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(nb::Error::WouldBlock), sensor.read_nb());
+        let (led_pin, _, _, _) = sensor.split();
+        assert!(led_pin.high);
+        assert_eq!(1, led_pin.set_high_calls);
+    }
+
+    #[test]
+    fn into_parts_turns_the_led_off_during_a_started_measurement() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        core::mem::forget(sensor.start_measurement().ok().unwrap());
+        let parts = sensor.into_parts();
+        assert!(parts.pin_led.high);
+    }
+
+    #[test]
+    fn read_batch_fills_the_buffer_a_cycle_apart() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 2, 3]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut buf = [0u16; 3];
+        assert_eq!(Ok(3), sensor.read_batch(&mut buf));
+        assert_eq!([1, 2, 3], buf);
+        assert_eq!(320_000 + 2 * 10_000_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_batch_returns_the_first_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut buf = [0u8; 3];
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), sensor.read_batch(&mut buf));
+    }
+
+    #[test]
+    fn warm_up_discards_full_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[900, 100]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.warm_up(3).ok().unwrap();
+        assert_eq!(3 * 320_000, sensor.delay().waited_ns);
+        assert_eq!(Ok(100), sensor.read());
+    }
+
+    #[test]
+    fn warm_up_returns_the_first_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), sensor.warm_up(3));
+        assert_eq!(280_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_with_timeout_gives_up_after_max_polls() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 5 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::Timeout), sensor.read_with_timeout(3));
+        assert!(sensor.pin_led().high);
+        assert_eq!(280_000, sensor.delay().waited_ns);
+        assert_eq!(Ok(42), sensor.read_with_timeout(3));
+    }
+
+    #[test]
+    fn read_with_yield_calls_the_callback_while_blocked() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 3 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        let mut yields = 0;
+        assert_eq!(Ok(42), sensor.read_with_yield(|| yields += 1));
+        assert_eq!(3, yields);
+    }
+
+    #[test]
+    fn read_keep_value_returns_the_led_off_error_with_the_reading() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let led_pin = TestFlakyPin { failures: 1, set_high_calls: 0 };
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .led_polarity(crate::sensor::LedPolarity::ActiveHigh)
+            .build().unwrap();
+        assert_eq!(Ok((10u8, Some(ErrorKind::Other))), sensor.read_keep_value());
+        assert_eq!(Ok((10u8, None)), sensor.read_keep_value());
+    }
+
+    #[test]
+    fn read_timestamped_takes_the_time_after_the_sampling_delay() {
+        struct SharedDelay<'a>(&'a core::cell::Cell<u64>);
+        impl DelayNs for SharedDelay<'_> {
+            fn delay_ns(&mut self, ns: u32) {
+                self.0.set(self.0.get() + ns as u64);
+            }
+        }
+        impl_eh02_delay!(SharedDelay<'_>);
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let waited_ns = core::cell::Cell::new(0);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, SharedDelay(&waited_ns));
+        let reading = sensor.read_timestamped(&|| waited_ns.get()).ok().unwrap();
+        assert_eq!(crate::reading::TimestampedReading { ts: 280_000, raw: 10u8 }, reading);
+        assert_eq!(320_000, waited_ns.get());
+    }
+
+    #[test]
+    fn error_predicates_match_the_variant() {
+        let led: crate::sensor::Error<ErrorKind, ()> = crate::sensor::Error::led(ErrorKind::Other);
+        let read: crate::sensor::Error<ErrorKind, ()> = crate::sensor::Error::read(());
+        assert!(led.is_led_error() && !led.is_read_error());
+        assert!(read.is_read_error() && !read.is_led_error());
+        assert!(!crate::sensor::Error::<ErrorKind, ()>::NotStarted.is_led_error());
+    }
+
+    #[test]
+    fn is_recoverable_only_for_transient_errors() {
+        assert!(crate::sensor::Error::<ErrorKind, ()>::led(ErrorKind::Other).is_recoverable());
+        assert!(crate::sensor::Error::<ErrorKind, ()>::Timeout.is_recoverable());
+        assert!(!crate::sensor::Error::<ErrorKind, ()>::read(()).is_recoverable());
+        assert!(!crate::sensor::Error::<ErrorKind, ()>::Saturated.is_recoverable());
+    }
+
+    #[test]
+    fn read_keep_value_returns_read_errors() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), sensor.read_keep_value());
+    }
+
+    #[test]
+    fn pre_sample_runs_before_every_conversion() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 2, 3]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .pre_sample(|adc: &mut TestSequenceAdc, _| adc.index += 1)
+            .build()
+            .unwrap();
+        assert_eq!(Ok(2), sensor.read());
+        sensor.set_pre_sample(None);
+        assert_eq!(Ok(3), sensor.read());
+    }
+
+    #[test]
+    fn read_dark_does_not_touch_the_led() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok(10u8), sensor.read_dark());
+        assert_eq!(0, sensor.pin_led().set_low_calls);
+        assert_eq!(0, sensor.pin_led().set_high_calls);
+        assert_eq!(280_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_channel_raw_only_converts() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSlowAdc { pending: 3 };
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok(42), sensor.read_channel_raw());
+        assert_eq!(0, sensor.pin_led().set_high_calls + sensor.pin_led().set_low_calls);
+        assert_eq!(0, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_channel_raw_returns_the_reader_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(()), sensor.read_channel_raw());
+    }
+
+    #[test]
+    fn self_test_passes_when_the_led_raises_the_output() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[100, 700]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let result = sensor.self_test().ok().unwrap();
+        assert_eq!(crate::diagnostic::DiagnosticResult { dark_mv: Millivolts(100.0), lit_mv: Millivolts(700.0), status: crate::diagnostic::DiagnosticStatus::Passed }, result);
+        assert_eq!(1, sensor.pin_led().set_low_calls);
+    }
+
+    #[test]
+    fn self_test_flags_readings_not_changing_with_the_led() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[500, 520]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(crate::diagnostic::DiagnosticStatus::NoLedResponse, sensor.self_test().ok().unwrap().status);
+    }
+
+    #[test]
+    fn self_test_flags_a_high_dark_output() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[800, 1200]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let result = sensor.self_test().ok().unwrap();
+        assert_eq!(crate::diagnostic::DiagnosticStatus::DarkTooHigh, result.status);
+        assert!(!result.passed());
+    }
+
+    #[test]
+    fn read_turns_the_led_off_when_one_shot_read_fails() {
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), sensor.read());
+        assert_eq!(1, sensor.pin_led().set_high_calls);
+        assert!(sensor.pin_led().high);
+        assert_eq!(280_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_turns_the_led_off_when_turning_it_on_fails() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestFlakyPin { failures: 1, set_high_calls: 0 }, data_pin, test_adc, TestDelay::new());
+        sensor.read().expect_err("Expected this function to error");
+        assert_eq!(1, sensor.pin_led().set_high_calls);
+    }
+
+    #[test]
+    fn new_without_delay_reads_without_timing_the_pulse() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new_without_delay(TestRecordingPin::new(), data_pin, test_adc);
+        assert_eq!(Ok(10), sensor.read());
+        assert!(sensor.pin_led().high);
+    }
+
+    #[test]
+    fn read_with_shares_one_adc_between_sensors() {
+        let mut test_adc = TestSequenceAdc::new(&[1, 2]);
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let mut first = crate::sensor::Gp2y1014au::new_shared(led_pin, data_pin, TestDelay::new());
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let mut second = crate::sensor::Gp2y1014au::new_shared(led_pin, data_pin, TestDelay::new());
+        assert_eq!(Ok(1), first.read_with(&mut test_adc));
+        assert_eq!(Ok(2), second.read_with(&mut test_adc));
+    }
+
+    #[test]
+    fn read_on_samples_the_given_channel() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, TestAdc::new(), TestDelay::new());
+        let mut other_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), sensor.read_on(&mut other_pin));
+        assert_eq!(280_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_powered_powers_the_adc_down_afterwards() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[42]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        sensor.power_down().ok().unwrap();
+        assert!(!sensor.reader().powered);
+        assert!(sensor.pin_led().high);
+        assert_eq!(Ok(42), sensor.read_powered());
+        assert!(!sensor.reader().powered);
+        sensor.power_up();
+        assert_eq!(Ok(42), sensor.read());
+    }
+
+    #[test]
+    fn iter_readings_reads_lazily() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 2, 3]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut buffer = [0u16; 2];
+        for (slot, reading) in buffer.iter_mut().zip(sensor.iter_readings()) {
+            *slot = reading.ok().unwrap();
+        }
+        assert_eq!([1, 2], buffer);
+        assert_eq!(2, sensor.reader().index);
+    }
+
+    #[test]
+    fn poll_measurement_samples_and_turns_the_led_off() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        let guard = sensor.start_measurement().ok().unwrap();
+        assert_eq!(Ok(10u8), guard.poll());
+        assert!(!sensor.is_measurement_started());
+        let (led_pin, _, _, _) = sensor.split();
+        assert!(led_pin.high);
+    }
+
+    #[test]
+    fn poll_measurement_requires_a_started_measurement() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::NotStarted), sensor.poll_measurement());
+        core::mem::forget(sensor.start_measurement().ok().unwrap());
+        assert!(sensor.is_measurement_started());
+        sensor.poll_measurement().ok().unwrap();
+        assert_eq!(Err(crate::sensor::Error::NotStarted), sensor.poll_measurement());
+    }
+
+    #[test]
+    fn dropping_the_measurement_guard_turns_the_led_off() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        drop(sensor.start_measurement().ok().unwrap());
+        assert!(!sensor.is_measurement_started());
+        assert!(sensor.pin_led().high);
+        assert_eq!(Err(crate::sensor::Error::NotStarted), sensor.poll_measurement());
+    }
+
+    impl <STATE> Channel<*const ()> for TestAnalogPin<STATE> {
+        type ID = u8;
+        fn channel() -> Self::ID {
+            1
+        }
+    }
+
+    impl OneShot<*const (), u8, TestAnalogPin<GoodState>> for () {
+        type Error = ();
+        fn read(&mut self, _: &mut TestAnalogPin<GoodState>) -> nb::Result<u8, ()> {
+            Ok(10u8)
+        }
+    }
+
+    #[test]
+    fn sensor_is_send_and_sync_for_any_adc_marker() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<crate::sensor::Gp2y1014au<TestOutputPin<GoodState>, (), *const (), u8, TestAnalogPin<GoodState>, TestDelay>>();
+    }
+
+    #[test]
+    fn accessors_borrow_the_peripherals() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 2, 3]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        sensor.read().ok().unwrap();
+        assert!(sensor.pin_led().high);
+        assert_eq!(320_000, sensor.delay().waited_ns);
+        sensor.reader_mut().index = 2;
+        assert_eq!(Ok(3), sensor.read());
+        sensor.pin_led_mut().set_low().ok().unwrap();
+        assert_eq!(3, sensor.pin_led().set_low_calls);
+    }
+
+    #[test]
+    fn replace_pins_return_the_old_pins() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 2]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        sensor.read().ok().unwrap();
+        let old_led = sensor.replace_pin_led(TestRecordingPin::new());
+        let _old_data = sensor.replace_pin_data(TestAnalogPin::new());
+        assert_eq!(1, old_led.set_low_calls);
+        assert_eq!(Ok(2), sensor.read());
+        assert_eq!(1, sensor.pin_led().set_low_calls);
+    }
+
+    #[test]
+    fn into_parts_names_the_peripherals() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        sensor.read().ok().unwrap();
+        let parts = sensor.into_parts();
+        assert!(parts.pin_led.high);
+        assert_eq!(1, parts.reader.index);
+        assert_eq!(320_000, parts.delay.waited_ns);
+    }
+
+    #[test]
+    fn builder_configures_the_led_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .sampling_delay_us(300)
+            .pulse_width_us(350)
+            .build().unwrap();
+        sensor.read().ok().unwrap();
+        let (_, _, _, delay) = sensor.split();
+        assert_eq!(350_000, delay.waited_ns);
+    }
+
+    #[test]
+    fn set_timing_configures_the_led_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let timing = crate::timing::Timing::new(250, 300, 10_000).unwrap();
+        sensor.set_timing(timing).unwrap();
+        sensor.read().ok().unwrap();
+        assert_eq!(timing, sensor.timing());
+        assert_eq!(300_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn set_timing_rejects_sampling_after_the_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let timing = crate::timing::Timing { sample_delay_us: 400, ..crate::timing::Timing::datasheet() };
+        assert_eq!(Err(crate::sensor::ConfigError::InvalidTiming), sensor.set_timing(timing));
+        assert_eq!(crate::timing::Timing::datasheet(), sensor.timing());
+    }
+
+    #[test]
+    fn build_rejects_an_invalid_timing() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let result = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).pulse_width_us(200).build();
+        assert_eq!(Some(crate::sensor::ConfigError::InvalidTiming), result.err());
+    }
+
+    #[test]
+    fn build_rejects_a_calibration_above_the_full_scale() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let calibration = crate::calibration::Calibration { v_oc_mv: Millivolts(3500.0), ..Default::default() };
+        let result = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).calibration(calibration).build();
+        assert_eq!(Some(crate::sensor::ConfigError::CalibrationOutOfRange), result.err());
+    }
+
+    #[test]
+    fn set_calibration_keeps_the_calibration_when_rejected() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let negative = crate::calibration::Calibration { sensitivity_mv_per_ug: -5.0, ..Default::default() };
+        assert_eq!(Err(crate::sensor::ConfigError::CalibrationOutOfRange), sensor.set_calibration(negative));
+        let calibration = crate::calibration::Calibration { v_oc_mv: Millivolts(700.0), ..Default::default() };
+        assert_eq!(Ok(()), sensor.set_calibration(calibration));
+        assert_eq!(calibration, sensor.calibration());
+    }
+
+    #[test]
+    fn read_voltage_scales_to_the_reference() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[2048]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Millivolts(1650.0), sensor.read_voltage().ok().unwrap());
+    }
+
+    #[test]
+    fn read_voltage_supports_u8_words() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).adc_bits(8).build().unwrap();
+        assert_eq!(Millivolts(128.90625), sensor.read_voltage().ok().unwrap());
+    }
+
+    #[test]
+    fn read_voltage_supports_u16_words() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[4095u16, 16383u16]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).adc_bits(12).build().unwrap();
+        assert_eq!(Millivolts(4095.0), sensor.read_voltage().ok().unwrap());
+        assert_eq!(Millivolts(4095.0), sensor.read_voltage().ok().unwrap());
+    }
+
+    #[test]
+    fn set_resolution_bits_rejects_resolutions_wider_than_the_word() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::ConfigError::ResolutionOutOfRange), sensor.set_resolution_bits(9));
+        assert_eq!(Err(crate::sensor::ConfigError::ResolutionOutOfRange), sensor.set_resolution_bits(0));
+        assert_eq!(Ok(()), sensor.set_resolution_bits(8));
+        assert_eq!(8, sensor.resolution_bits());
+    }
+
+    #[test]
+    fn default_resolution_is_capped_to_the_word() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, TestAdc::new(), TestDelay::new());
+        assert_eq!(8, sensor.resolution_bits());
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, TestAdc::new(), TestDelay::new()).build().unwrap();
+        assert_eq!(8, sensor.resolution_bits());
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, TestSequenceAdc::new(&[0]), TestDelay::new());
+        assert_eq!(12, sensor.resolution_bits());
+    }
+
+    #[test]
+    fn build_rejects_an_invalid_resolution() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[512]);
+        let result = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).adc_bits(64).build();
+        assert!(matches!(result, Err(crate::sensor::ConfigError::ResolutionOutOfRange)));
+    }
+
+    #[test]
+    fn read_voltage_uses_the_configured_reference_and_resolution() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[512]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.set_reference_mv(Millivolts(5000.0)).unwrap();
+        sensor.set_resolution_bits(10).unwrap();
+        assert_eq!(Millivolts(2500.0), sensor.read_voltage().ok().unwrap());
+    }
+
+    #[test]
+    fn read_voltage_scales_by_the_divider_ratio() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1024]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).divider_ratio(1.5).build().unwrap();
+        assert_eq!(Millivolts(1536.0), sensor.read_voltage().ok().unwrap());
+    }
+
+    #[test]
+    fn read_voltage_averages_the_samples_of_one_pulse() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 1001, 1003, 1000]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new())
+            .reference_mv(Millivolts(4096.0))
+            .samples_per_pulse(4)
+            .build().unwrap();
+        assert_eq!(Millivolts(1001.0), sensor.read_voltage().ok().unwrap());
+        assert_eq!(1, sensor.pin_led().set_low_calls);
+        assert_eq!(320_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_averages_the_samples_of_one_pulse() {
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 1001, 1003, 1000]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new())
+            .samples_per_pulse(4)
+            .build().unwrap();
+        assert_eq!(Ok(1001), sensor.read());
+        assert_eq!(Some(1001), sensor.last_raw());
+        assert_eq!(Ok(1001), sensor.read_median::<3>());
+        assert_eq!(16, sensor.reader().index);
+        assert_eq!(4, sensor.pin_led().set_low_calls);
+    }
+
+    #[test]
+    fn read_nb_averages_the_samples_of_one_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 2000, 1000, 1001]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .samples_per_pulse(3)
+            .discard_first(1)
+            .build().unwrap();
+        assert_eq!(Ok(1334), sensor.read_nb());
+        assert_eq!(4, sensor.reader().index);
+    }
+
+    #[test]
+    fn set_samples_per_pulse_rejects_zero() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, TestAdc::new(), TestDelay::new());
+        assert_eq!(Err(crate::sensor::ConfigError::SamplesOutOfRange), sensor.set_samples_per_pulse(0));
+        assert_eq!(1, sensor.samples_per_pulse());
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let result = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, TestAdc::new(), TestDelay::new()).samples_per_pulse(0).build();
+        assert_eq!(Some(crate::sensor::ConfigError::SamplesOutOfRange), result.err());
+    }
+
+    #[test]
+    fn discard_first_drops_the_first_conversions_of_each_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 2000, 3000]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .reference_mv(Millivolts(4096.0))
+            .samples_per_pulse(2)
+            .discard_first(1)
+            .build().unwrap();
+        assert_eq!(1, sensor.discard_first());
+        assert_eq!(Ok(Millivolts(2500.0)), sensor.read_voltage());
+        sensor.set_discard_first(2);
+        assert_eq!(Ok(2000), sensor.read());
+        assert_eq!(320_000 * 2, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn discard_first_applies_to_read_with_timeout_yield_and_nb() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 2000, 3000, 4000, 5000, 6000]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .discard_first(1)
+            .build().unwrap();
+        assert_eq!(Ok(2000), sensor.read_with_timeout(3));
+        assert_eq!(2, sensor.reader().index);
+        let mut yields = 0;
+        assert_eq!(Ok(4000), sensor.read_with_yield(|| yields += 1));
+        assert_eq!(0, yields);
+        assert_eq!(4, sensor.reader().index);
+        assert_eq!(Ok(6000), sensor.read_nb());
+        assert_eq!(6, sensor.reader().index);
+    }
+
+    #[test]
+    fn read_voltage_aborts_the_pulse_on_the_first_read_error() {
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(TestRecordingPin::new(), data_pin, test_adc, TestDelay::new());
+        sensor.set_samples_per_pulse(4).unwrap();
+        assert_eq!(Err(crate::sensor::Error::ReadError(())), sensor.read_voltage());
+        assert!(sensor.pin_led().high);
+        assert_eq!(280_000, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn read_voltage_f64_keeps_the_precision() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 1200]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(3300.0 / 4096.0, sensor.read_voltage_f64().ok().unwrap());
+        sensor.set_reference_mv(Millivolts(4096.0)).unwrap();
+        assert_eq!(120.0, sensor.read_dust_density_f64().ok().unwrap());
+    }
+
+    #[test]
+    fn set_divider_ratio_rejects_amplification() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::ConfigError::DividerOutOfRange), sensor.set_divider_ratio(0.5));
+        assert_eq!(Err(crate::sensor::ConfigError::DividerOutOfRange), sensor.set_divider_ratio(f32::NAN));
+        assert_eq!(1.0, sensor.divider_ratio());
+    }
+
+    #[test]
+    fn set_reference_mv_rejects_zero() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::ConfigError::ReferenceOutOfRange), sensor.set_reference_mv(Millivolts(0.0)));
+        assert_eq!(Millivolts(3300.0), sensor.reference_mv());
+    }
+
+    #[test]
+    fn set_reference_mv_rounds_to_whole_millivolts() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok(()), sensor.set_reference_mv(Millivolts(4095.6)));
+        assert_eq!(Millivolts(4096.0), sensor.reference_mv());
+        assert_eq!(Err(crate::sensor::ConfigError::ReferenceOutOfRange), sensor.set_reference_mv(Millivolts(0.4)));
+        assert_eq!(Err(crate::sensor::ConfigError::ReferenceOutOfRange), sensor.set_reference_mv(Millivolts(70_000.0)));
+        assert_eq!(Err(crate::sensor::ConfigError::ReferenceOutOfRange), sensor.set_reference_mv(Millivolts(f32::NAN)));
+        assert_eq!(Millivolts(4096.0), sensor.reference_mv());
+    }
+
+    #[test]
+    fn reference_and_divider_setters_keep_the_calibration_in_range() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::ConfigError::CalibrationOutOfRange), sensor.set_reference_mv(Millivolts(500.0)));
+        assert_eq!(Millivolts(3300.0), sensor.reference_mv());
+        assert_eq!(Ok(()), sensor.set_divider_ratio(2.0));
+        assert_eq!(Ok(()), sensor.set_reference_mv(Millivolts(500.0)));
+        assert_eq!(Err(crate::sensor::ConfigError::CalibrationOutOfRange), sensor.set_divider_ratio(1.0));
+        assert_eq!(2.0, sensor.divider_ratio());
+    }
+
+    #[test]
+    fn build_rejects_an_invalid_reference_or_divider() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let result = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, TestAdc::new(), TestDelay::new()).reference_mv(Millivolts(0.0)).build();
+        assert_eq!(Some(crate::sensor::ConfigError::ReferenceOutOfRange), result.err());
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let result = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, TestAdc::new(), TestDelay::new()).divider_ratio(0.5).build();
+        assert_eq!(Some(crate::sensor::ConfigError::DividerOutOfRange), result.err());
+    }
+
+    #[test]
+    fn read_dust_density_uses_the_configured_calibration() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[2048]);
+        let calibration = crate::calibration::Calibration { v_oc_mv: Millivolts(650.0), sensitivity_mv_per_ug: 10.0 };
+        let mut sensor = crate::sensor::Gp2y1014au::with_calibration(led_pin, data_pin, test_adc, TestDelay::new(), calibration);
+        assert_eq!(MicrogramsPerM3(100.0), sensor.read_dust_density().ok().unwrap());
+    }
+
+    #[test]
+    fn read_dust_density_in_converts_to_milligrams() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1100]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Ok(0.1), sensor.read_dust_density_in(crate::conversion::DensityUnit::MilligramsPerM3));
+    }
+
+    #[test]
+    fn conversions_from_raw_do_not_touch_the_hardware() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[0]);
+        let sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Millivolts(1100.0), sensor.voltage_from_raw(1100));
+        assert_eq!(MicrogramsPerM3(100.0), sensor.density_from_raw(1100));
+        assert_eq!(0, sensor.reader().index);
+        assert_eq!(0, sensor.delay().waited_ns);
+    }
+
+    #[test]
+    fn invert_data_subtracts_the_reading_from_the_reference() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[2996, 0]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .reference_mv(Millivolts(4096.0))
+            .invert_data(true)
+            .build()
+            .unwrap();
+        assert!(sensor.invert_data());
+        assert_eq!(Ok(MicrogramsPerM3(100.0)), sensor.read_dust_density());
+        assert_eq!(Err(crate::sensor::Error::Saturated), sensor.read_dust_density());
+    }
+
+    #[test]
+    fn last_reading_caches_the_last_conversion() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1100, 700]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(None, sensor.last_reading());
+        sensor.measure().ok().unwrap();
+        assert_eq!(Some(1100), sensor.last_raw());
+        assert_eq!(Ok(700), sensor.read());
+        assert_eq!(Some(700), sensor.last_raw());
+        assert_eq!(Some(crate::reading::Reading { raw: 700, voltage_mv: Millivolts(700.0), density_ug_m3: MicrogramsPerM3(20.0) }), sensor.last_reading());
+        assert_eq!(2, sensor.reader().index);
+    }
+
+    #[test]
+    fn read_dust_density_clamps_clean_air_to_zero() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[100]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(MicrogramsPerM3(0.0), sensor.read_dust_density().ok().unwrap());
+    }
+
+    #[test]
+    fn with_config_sets_the_adc_parameters() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[512]);
+        let mut sensor = crate::sensor::Gp2y1014au::with_config(led_pin, data_pin, test_adc, TestDelay::new(), Millivolts(5000.0), 10).unwrap();
+        assert_eq!(Millivolts(5000.0), sensor.reference_mv());
+        assert_eq!(crate::timing::Timing::datasheet(), sensor.timing());
+        assert_eq!(Ok(Millivolts(2500.0)), sensor.read_voltage());
+    }
+
+    #[test]
+    fn with_model_loads_the_model_calibration() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000]);
+        let mut sensor = crate::sensor::Gp2y1014au::with_model(led_pin, data_pin, test_adc, TestDelay::new(), crate::model::Model::Gp2y1010au);
+        sensor.set_reference_mv(Millivolts(4096.0)).unwrap();
+        assert_eq!(crate::model::Model::Gp2y1010au.calibration(), sensor.calibration());
+        assert_eq!(MicrogramsPerM3(20.0), sensor.read_dust_density().ok().unwrap());
+    }
+
+    #[test]
+    fn read_quality_score_converts_the_density() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[500]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Ok(100), sensor.read_quality_score());
+    }
+
+    #[test]
+    fn read_particle_count_converts_the_density() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1100, 500]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Ok(crate::conversion::particles_per_0_01cf(MicrogramsPerM3(100.0))), sensor.read_particle_count());
+        assert_eq!(Ok(0.0), sensor.read_particle_count());
+    }
+
+    #[test]
+    fn read_dust_density_rejects_saturated_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[4095, 4090, 4080]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Err(crate::sensor::Error::Saturated), sensor.read_dust_density());
+        sensor.set_saturation_margin(10);
+        assert_eq!(Err(crate::sensor::Error::Saturated), sensor.read_dust_density());
+        assert_eq!(Ok(MicrogramsPerM3(696.0)), sensor.read_dust_density());
+    }
+
+    #[test]
+    fn read_all_rejects_saturated_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[4095]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::Saturated), sensor.read_all());
+    }
+
+    #[test]
+    fn read_dust_density_with_uses_the_converter() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Ok(MicrogramsPerM3(500.0)), sensor.read_dust_density_with(&|voltage: Millivolts| MicrogramsPerM3(voltage.0 / 2.0)));
+    }
+
+    #[test]
+    fn read_dust_density_curve_uses_the_curve() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let curve = crate::calibration::CalibrationCurve::new([(Millivolts(600.0), MicrogramsPerM3(0.0)), (Millivolts(800.0), MicrogramsPerM3(20.0))]).unwrap();
+        assert_eq!(MicrogramsPerM3(10.0), sensor.read_dust_density_curve(&curve).ok().unwrap());
+    }
+
+    #[test]
+    fn read_lowpass_filters_the_voltage() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700, 800]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let mut low_pass = crate::filter::IirLowPass::new(10.0, 100.0).unwrap();
+        assert_eq!(Ok(Millivolts(700.0)), sensor.read_lowpass(&mut low_pass));
+        assert_eq!(Ok(Millivolts(700.0 + low_pass.alpha() * 100.0)), sensor.read_lowpass(&mut low_pass));
+    }
+
+    #[test]
+    fn read_despiked_replaces_spikes() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700, 710, 700, 3000]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let mut hampel: crate::filter::HampelFilter<3> = crate::filter::HampelFilter::new(3.0).unwrap();
+        for _ in 0..3 {
+            sensor.read_despiked(&mut hampel).ok().unwrap();
+        }
+        assert_eq!(Ok(Millivolts(700.0)), sensor.read_despiked(&mut hampel));
+    }
+
+    #[test]
+    fn read_trimmed_mean_drops_the_extremes() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[900, 10, 12, 0, 14]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok(12.0), sensor.read_trimmed_mean::<5>(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn read_trimmed_mean_rejects_trimming_every_reading() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let _ = sensor.read_trimmed_mean::<4>(2);
+    }
+
+    #[test]
+    fn read_snr_divides_the_mean_by_the_sample_std_dev() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[8, 10, 12]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Ok(5.0), sensor.read_snr::<3>());
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, TestAdc::new(), TestDelay::new());
+        assert_eq!(Ok(f32::INFINITY), sensor.read_snr::<4>());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn read_into_vec_fills_the_remaining_capacity() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1, 2, 3]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut vec: heapless::Vec<u16, 4> = heapless::Vec::new();
+        vec.push(7).unwrap();
+        assert_eq!(Ok(3), sensor.read_into_vec(&mut vec));
+        assert_eq!(&[7, 1, 2, 3], vec.as_slice());
+        assert_eq!(Ok(0), sensor.read_into_vec(&mut vec));
+    }
+
+    #[test]
+    fn read_quantized_holds_small_changes() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700, 705, 800]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let mut deadband = crate::filter::Deadband::new(5.0).unwrap();
+        assert_eq!(Ok(MicrogramsPerM3(20.0)), sensor.read_quantized(&mut deadband));
+        assert_eq!(Ok(MicrogramsPerM3(20.0)), sensor.read_quantized(&mut deadband));
+        assert_eq!(Ok(MicrogramsPerM3(40.0)), sensor.read_quantized(&mut deadband));
+    }
+
+    #[test]
+    fn read_filtered_feeds_the_density_through_the_filter() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700, 800]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let mut kalman = crate::filter::Kalman1D::new(0.0, 1.0).unwrap();
+        assert_eq!(Ok(MicrogramsPerM3(20.0)), sensor.read_filtered(&mut kalman));
+        assert_eq!(Ok(MicrogramsPerM3(30.0)), sensor.read_filtered(&mut kalman));
+    }
+
+    #[test]
+    fn read_threshold_updates_the_alert_state() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1200, 1200, 700]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let mut threshold = crate::threshold::Threshold::new(50.0, 100.0).unwrap();
+        assert_eq!(Ok(Some(crate::threshold::Crossing::Entered)), sensor.read_threshold(&mut threshold));
+        assert_eq!(Ok(None), sensor.read_threshold(&mut threshold));
+        assert_eq!(Ok(Some(crate::threshold::Crossing::Exited)), sensor.read_threshold(&mut threshold));
+    }
+
+    #[test]
+    fn read_dust_density_compensated_adjusts_the_no_dust_voltage() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1100]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new())
+            .reference_mv(Millivolts(4096.0))
+            .temperature_coefficient_mv_per_c(2.0)
+            .build().unwrap();
+        assert_eq!(MicrogramsPerM3(100.0), sensor.read_dust_density_compensated(25.0).ok().unwrap());
+        assert_eq!(MicrogramsPerM3(98.0), sensor.read_dust_density_compensated(30.0).ok().unwrap());
+        assert_eq!(MicrogramsPerM3(0.0), sensor.read_dust_density_compensated(300.0).ok().unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "fixed-point")]
+    fn read_dust_density_ug_fixed_matches_the_float_path() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1365]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let calibration = crate::fixed::FixedCalibration::datasheet();
+        assert_eq!(1100, sensor.read_voltage_mv_i32().ok().unwrap());
+        assert_eq!(100_000, sensor.read_dust_density_ug_fixed(&calibration).ok().unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "fixed-point")]
+    fn read_voltage_mv_i32_scales_by_the_divider_ratio() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[800]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).divider_ratio(1.5).build().unwrap();
+        let calibration = crate::fixed::FixedCalibration::datasheet();
+        assert_eq!(1200, sensor.read_voltage_mv_i32().ok().unwrap());
+        assert_eq!(sensor.read_voltage().ok().unwrap().0 as i32, sensor.read_voltage_mv_i32().ok().unwrap());
+        assert_eq!(120_000, sensor.read_dust_density_ug_fixed(&calibration).ok().unwrap());
+    }
+
+    #[test]
+    fn read_aqi_converts_the_dust_density() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[645]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(50, sensor.read_aqi().ok().unwrap());
+    }
+
+    #[test]
+    fn read_all_converts_a_single_reading() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1100, 0]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let reading = sensor.read_all().ok().unwrap();
+        assert_eq!(crate::reading::Reading { raw: 1100, voltage_mv: Millivolts(1100.0), density_ug_m3: MicrogramsPerM3(100.0) }, reading);
+    }
+
+    #[test]
+    fn read_all_averages_the_samples_of_one_pulse() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1000, 1200]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).samples_per_pulse(2).build().unwrap();
+        let voltage = sensor.read_voltage().ok().unwrap();
+        let reading = sensor.read_all().ok().unwrap();
+        assert_eq!(crate::reading::Reading { raw: 1100, voltage_mv: voltage, density_ug_m3: MicrogramsPerM3(100.0) }, reading);
+        assert_eq!(Some(reading), sensor.last_reading());
+    }
+
+    #[test]
+    fn read_as_converts_to_the_requested_type() {
+        use crate::reading::Raw;
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1100]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Raw(1100), sensor.read_as().ok().unwrap());
+        assert_eq!(Millivolts(1100.0), sensor.read_as().ok().unwrap());
+        assert_eq!(MicrogramsPerM3(100.0), sensor.read_as().ok().unwrap());
+    }
+
+    #[test]
+    fn read_windowed_weights_the_burst() {
+        use crate::filter::WindowShape;
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[10, 20, 60]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(30.0, sensor.read_windowed::<3>(WindowShape::Flat).ok().unwrap());
+        let hamming = sensor.read_windowed::<3>(WindowShape::Hamming).ok().unwrap();
+        assert!((hamming - 25.6 / 1.16).abs() < 1e-3, "unexpected mean {}", hamming);
+    }
+
+    #[test]
+    fn read_averaged_returns_the_mean_of_n_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[10, 20, 60]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(30.0, sensor.read_averaged::<3>().ok().unwrap());
+    }
+
+    #[test]
+    fn read_averaged_returns_the_first_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.read_averaged::<3>().expect_err("Expected this function to error");
+    }
+
+    #[test]
+    fn read_weighted_feeds_the_window() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[10, 40]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut window: crate::filter::WeightedWindow<4> = crate::filter::WeightedWindow::new();
+        assert_eq!(Ok(10.0), sensor.read_weighted(&mut window));
+        assert_eq!(Ok(30.0), sensor.read_weighted(&mut window));
+    }
+
+    #[test]
+    fn read_ema_smooths_consecutive_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[100, 200]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut ema = crate::filter::ExponentialMovingAverage::new(0.25).unwrap();
+        assert_eq!(100.0, sensor.read_ema(&mut ema).ok().unwrap());
+        assert_eq!(125.0, sensor.read_ema(&mut ema).ok().unwrap());
+    }
+
+    #[test]
+    fn read_median_returns_the_middle_reading() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[30, 900, 20]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(30, sensor.read_median::<3>().ok().unwrap());
+    }
+
+    #[test]
+    fn read_median_returns_the_lower_middle_reading_for_even_n() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[40, 10, 30, 20]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(20, sensor.read_median::<4>().ok().unwrap());
+    }
+
+    #[test]
+    fn read_percentile_uses_the_nearest_rank() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[50, 10, 40, 20, 30, 100, 60, 90, 70, 80]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(90, sensor.read_percentile::<10>(0.9).ok().unwrap());
+        assert_eq!(10, sensor.read_percentile::<10>(0.0).ok().unwrap());
+        assert_eq!(100, sensor.read_percentile::<10>(1.0).ok().unwrap());
+        assert_eq!(50, sensor.read_percentile::<10>(0.45).ok().unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn read_percentile_rejects_fractions_out_of_range() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let _ = sensor.read_percentile::<4>(1.5);
+    }
+
+    #[test]
+    fn read_stats_returns_the_burst_statistics() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[30, 10, 50, 30]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let stats = sensor.read_stats::<4>().ok().unwrap();
+        assert_eq!(crate::stats::SampleStats { min: 10, max: 50, mean: 30.0, count: 4 }, stats);
+    }
+
+    #[test]
+    fn read_into_stats_accumulates_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[10, 30]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut stats = crate::stats::Stats::new();
+        sensor.read_into_stats(&mut stats).ok().unwrap();
+        sensor.read_into_stats(&mut stats).ok().unwrap();
+        assert_eq!(2, stats.count());
+        assert_eq!(20.0, stats.mean());
+        assert_eq!(10.0, stats.std_dev());
+    }
+
+    #[test]
+    fn read_into_histogram_records_readings() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[100, 3000]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        let mut histogram: crate::stats::Histogram<2> = crate::stats::Histogram::new(0.0, 4096.0).unwrap();
+        sensor.read_into_histogram(&mut histogram).ok().unwrap();
+        sensor.read_into_histogram(&mut histogram).ok().unwrap();
+        assert_eq!(&[1, 1], histogram.bins());
+    }
+
+    #[test]
+    fn read_stats_returns_the_first_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.read_stats::<4>().expect_err("Expected this function to error");
+    }
+
+    #[test]
+    fn read_oversampled_adds_bits_of_resolution() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[100, 101, 101, 101]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(201, sensor.read_oversampled::<1>().ok().unwrap());
+        assert_eq!(4, sensor.reader().index);
+    }
+
+    #[test]
+    fn calibrate_zero_stores_the_average_voltage() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700, 900]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        sensor.calibrate_zero(4).ok().unwrap();
+        assert_eq!(Millivolts(800.0), sensor.calibration().v_oc_mv);
+    }
+
+    #[test]
+    fn measure_noise_floor_stores_the_standard_deviation() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[790, 810]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(None, sensor.noise_floor_ug_m3());
+        let noise_floor = sensor.measure_noise_floor(4).ok().unwrap();
+        assert!((noise_floor.0 - 11.547).abs() < 0.01, "unexpected noise floor {:?}", noise_floor);
+        assert_eq!(Some(noise_floor), sensor.noise_floor_mv());
+        assert_eq!(Some(MicrogramsPerM3(noise_floor.0 / 5.0)), sensor.noise_floor_ug_m3());
+    }
+
+    #[test]
+    fn calibrate_zero_keeps_the_calibration_on_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let test_adc: TestAdc = TestAdc::new();
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.calibrate_zero(4).expect_err("Expected this function to error");
+        assert_eq!(crate::calibration::Calibration::datasheet(), sensor.calibration());
+    }
+
+    #[test]
+    fn uncalibrated_sensor_reads_densities_once_calibrated() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700, 1100]);
+        let mut sensor = crate::sensor::Gp2y1014au::new_uncalibrated(led_pin, data_pin, test_adc, TestDelay::new());
+        sensor.set_reference_mv(Millivolts(4096.0)).unwrap();
+        let mut sensor = sensor.calibrate_zero(1).ok().unwrap();
+        assert_eq!(Millivolts(700.0), sensor.calibration().v_oc_mv);
+        assert_eq!(Ok(MicrogramsPerM3(80.0)), sensor.read_dust_density());
+    }
+
+    #[test]
+    fn calibrate_replaces_the_calibration() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[2048]);
+        let sensor = crate::sensor::Gp2y1014au::new_uncalibrated(led_pin, data_pin, test_adc, TestDelay::new());
+        let calibration = crate::calibration::Calibration { v_oc_mv: Millivolts(650.0), sensitivity_mv_per_ug: 10.0 };
+        let mut sensor = sensor.calibrate(calibration).ok().unwrap();
+        assert_eq!(Ok(MicrogramsPerM3(100.0)), sensor.read_dust_density());
+    }
+
+    #[test]
+    fn uncalibrated_calibrate_zero_requires_samples() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700]);
+        let sensor = crate::sensor::Gp2y1014au::new_uncalibrated(led_pin, data_pin, test_adc, TestDelay::new());
+        let (sensor, error) = sensor.calibrate_zero(0).err().unwrap();
+        assert_eq!(crate::sensor::Error::InvalidCalibration(crate::sensor::ConfigError::CalibrationOutOfRange), error);
+        assert_eq!(0, sensor.reader().index);
+    }
+
+    #[test]
+    fn calibrate_zero_requires_samples() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[700]);
+        let mut sensor = crate::sensor::Gp2y1014au::new(led_pin, data_pin, test_adc, TestDelay::new());
+        assert_eq!(Err(crate::sensor::Error::InvalidCalibration(crate::sensor::ConfigError::CalibrationOutOfRange)), sensor.calibrate_zero(0));
+        assert_eq!(crate::calibration::Calibration::datasheet(), sensor.calibration());
+    }
+
+    #[test]
+    fn uncalibrated_calibrate_zero_hands_back_the_sensor_on_error() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<BadState> = TestAnalogPin::new();
+        let sensor = crate::sensor::Gp2y1014au::new_uncalibrated(led_pin, data_pin, TestAdc::new(), TestDelay::new());
+        let (sensor, error) = sensor.calibrate_zero(4).err().unwrap();
+        assert_eq!(crate::sensor::Error::ReadError(()), error);
+        let calibration = crate::calibration::Calibration { v_oc_mv: Millivolts(650.0), sensitivity_mv_per_ug: 10.0 };
+        let sensor = sensor.calibrate(calibration).ok().unwrap();
+        assert_eq!(calibration, sensor.calibration());
+    }
+
+    #[test]
+    fn calibrate_rejects_an_invalid_calibration() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[2048]);
+        let sensor = crate::sensor::Gp2y1014au::new_uncalibrated(led_pin, data_pin, test_adc, TestDelay::new());
+        let calibration = crate::calibration::Calibration { v_oc_mv: Millivolts(650.0), sensitivity_mv_per_ug: 0.0 };
+        let (sensor, error) = sensor.calibrate(calibration).err().unwrap();
+        assert_eq!(crate::sensor::ConfigError::CalibrationOutOfRange, error);
+        assert_eq!(crate::calibration::Calibration::datasheet(), sensor.calibration());
+    }
+
+    #[test]
+    fn calibrate_span_solves_for_the_sensitivity() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1500, 1700]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        sensor.calibrate_span(MicrogramsPerM3(100.0), 2).ok().unwrap();
+        assert_eq!(10.0, sensor.calibration().sensitivity_mv_per_ug);
+        assert_eq!(Millivolts(600.0), sensor.calibration().v_oc_mv);
+    }
+
+    #[test]
+    fn calibrate_span_rejects_readings_at_the_baseline() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[600]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        assert_eq!(Err(crate::sensor::Error::BelowBaseline), sensor.calibrate_span(MicrogramsPerM3(100.0), 3));
+        assert_eq!(crate::calibration::Calibration::datasheet(), sensor.calibration());
+    }
+
+    #[test]
+    fn calibrate_span_rejects_a_zero_sensitivity() {
+        let led_pin: TestOutputPin<GoodState> = TestOutputPin::new();
+        let data_pin: TestAnalogPin<GoodState> = TestAnalogPin::new();
+        let test_adc = TestSequenceAdc::new(&[1600]);
+        let mut sensor = crate::sensor::Gp2y1014au::builder(led_pin, data_pin, test_adc, TestDelay::new()).reference_mv(Millivolts(4096.0)).build().unwrap();
+        let result = sensor.calibrate_span(MicrogramsPerM3(f32::INFINITY), 1);
+        assert_eq!(Err(crate::sensor::Error::InvalidCalibration(crate::sensor::ConfigError::CalibrationOutOfRange)), result);
+        assert_eq!(crate::calibration::Calibration::datasheet(), sensor.calibration());
     }
     
 
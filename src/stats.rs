@@ -0,0 +1,329 @@
+use crate::sensor::ConfigError;
+
+/// Statistics of a burst of readings.
+///
+/// With the `serde` feature enabled, `Serialize` and `Deserialize` are implemented whenever `Word` implements them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampleStats<Word> {
+    /// The lowest reading.
+    pub min: Word,
+    /// The highest reading.
+    pub max: Word,
+    /// The arithmetic mean of the readings.
+    pub mean: f32,
+    /// The number of readings.
+    pub count: usize,
+}
+
+/// Running mean and variance over any number of readings, using constant memory.
+///
+/// Values are accumulated with Welford's online algorithm, which stays accurate over long runs
+/// where summing the squares of the readings would lose precision.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    count: u32,
+    mean: f32,
+    m2: f32,
+}
+
+impl Stats {
+    /// Creates an empty accumulator.
+    pub const fn new() -> Self {
+        Self { count: 0, mean: 0.0, m2: 0.0 }
+    }
+
+    /// Adds a value, such as a raw reading, to the statistics.
+    pub fn push(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Returns the number of values pushed.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the mean of the values pushed, or 0.0 if none were pushed.
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    /// Returns the population variance of the values pushed, or 0.0 if none were pushed.
+    pub fn variance(&self) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.m2 / self.count as f32
+    }
+
+    /// Returns the population standard deviation of the values pushed, or 0.0 if none were pushed.
+    pub fn std_dev(&self) -> f32 {
+        libm::sqrtf(self.variance())
+    }
+
+    /// Returns the sample variance of the values pushed, or 0.0 if fewer than two were pushed.
+    pub fn sample_variance(&self) -> f32 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.count - 1) as f32
+    }
+
+    /// Returns the sample standard deviation of the values pushed, or 0.0 if fewer than two were pushed.
+    pub fn sample_std_dev(&self) -> f32 {
+        libm::sqrtf(self.sample_variance())
+    }
+
+    /// Forgets every value pushed so far.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Histogram of values, such as raw readings, counted into `BINS` equally wide bins.
+///
+/// The bins split `min..max`, values outside of the range are counted into the first or last bin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram<const BINS: usize> {
+    min: f32,
+    max: f32,
+    bins: [u32; BINS],
+}
+
+impl<const BINS: usize> Histogram<BINS> {
+    /// Creates an empty histogram over `min..max`.
+    ///
+    /// Returns `ConfigError::EmptyRange` unless `min` is below `max`.
+    pub fn new(min: f32, max: f32) -> Result<Self, ConfigError> {
+        const { assert!(BINS > 0, "Histogram needs at least one bin") };
+        if min.is_nan() || max.is_nan() || min >= max {
+            return Err(ConfigError::EmptyRange);
+        }
+        Ok(Self { min, max, bins: [0; BINS] })
+    }
+
+    /// Counts a value into its bin.
+    pub fn record(&mut self, value: f32) {
+        let position = (value - self.min) / (self.max - self.min) * BINS as f32;
+        let bin = if position > 0.0 { (position as usize).min(BINS - 1) } else { 0 };
+        self.bins[bin] = self.bins[bin].saturating_add(1);
+    }
+
+    /// Returns the counts of all bins, from the lowest to the highest values.
+    pub fn bins(&self) -> &[u32; BINS] {
+        &self.bins
+    }
+
+    /// Returns the count of the bin at `index`, or `None` if there is no such bin.
+    pub fn count(&self, index: usize) -> Option<u32> {
+        self.bins.get(index).copied()
+    }
+
+    /// Returns the index of the bin with the highest count, or `None` if nothing was recorded.
+    ///
+    /// Ties are resolved in favor of the lowest bin.
+    pub fn mode(&self) -> Option<usize> {
+        let (index, &count) = self.bins.iter().enumerate().rev().max_by_key(|&(_, count)| count)?;
+        if count == 0 { None } else { Some(index) }
+    }
+
+    /// Clears the counts of all bins while keeping the range.
+    pub fn reset(&mut self) {
+        self.bins = [0; BINS];
+    }
+
+    /// Returns the values covered by the bin at `index`.
+    pub fn bin_range(&self, index: usize) -> core::ops::Range<f32> {
+        let width = (self.max - self.min) / BINS as f32;
+        (self.min + width * index as f32)..(self.min + width * (index + 1) as f32)
+    }
+}
+
+/// The last `N` readings in a ring buffer, for windowed statistics during continuous monitoring.
+///
+/// Readings are pushed one at a time, typically from repeated `Gp2y1014au::read` calls. The statistics
+/// sort a copy of the window on the stack when queried, so pushing stays cheap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RollingWindow<Word, const N: usize> {
+    samples: [Option<Word>; N],
+    next: usize,
+}
+
+impl<Word: Ord + Copy, const N: usize> RollingWindow<Word, N> {
+    /// Creates an empty window.
+    pub fn new() -> Self {
+        const { assert!(N > 0, "RollingWindow needs room for at least one reading") };
+        Self { samples: [None; N], next: 0 }
+    }
+
+    /// Adds a reading, replacing the oldest one once the window is full.
+    pub fn push(&mut self, word: Word) {
+        self.samples[self.next] = Some(word);
+        self.next = (self.next + 1) % N;
+    }
+
+    /// Empties the window.
+    pub fn reset(&mut self) {
+        self.samples = [None; N];
+        self.next = 0;
+    }
+
+    /// Returns the number of readings in the window.
+    pub fn len(&self) -> usize {
+        self.samples.iter().filter(|sample| sample.is_some()).count()
+    }
+
+    /// Returns true if no reading was pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.samples[0].is_none()
+    }
+
+    /// Returns the median of the window, or `None` if it is empty.
+    ///
+    /// For an even number of readings the lower of the two middle readings is returned, as for `Gp2y1014au::read_median`.
+    pub fn median(&self) -> Option<Word> {
+        let len = self.len();
+        self.sorted_nth(len.checked_sub(1)? / 2)
+    }
+
+    /// Returns the reading at the percentile `p` of the window using the nearest-rank method, or `None` if it is empty.
+    ///
+    /// `p` is a fraction as for `Gp2y1014au::read_percentile`.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if `p` is not within `0.0..=1.0`. Release builds clamp it to that range.
+    pub fn percentile(&self, p: f32) -> Option<Word> {
+        debug_assert!((0.0..=1.0).contains(&p), "the percentile must be within 0.0..=1.0");
+        let len = self.len();
+        let rank = libm::ceilf(p.clamp(0.0, 1.0) * len as f32) as usize;
+        self.sorted_nth(rank.clamp(1, len).checked_sub(1)?)
+    }
+
+    /// Returns the reading at `index` of the sorted window.
+    fn sorted_nth(&self, index: usize) -> Option<Word> {
+        let mut sorted = self.samples;
+        sorted.sort_unstable();
+        let empty = N - self.len();
+        sorted[empty..].get(index).copied().flatten()
+    }
+}
+
+impl<Word: Ord + Copy, const N: usize> Default for RollingWindow<Word, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Histogram, RollingWindow, Stats};
+    use crate::sensor::ConfigError;
+
+    #[test]
+    fn stats_computes_mean_and_variance() {
+        let mut stats = Stats::new();
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.push(value);
+        }
+        assert_eq!(8, stats.count());
+        assert_eq!(5.0, stats.mean());
+        assert_eq!(4.0, stats.variance());
+        assert_eq!(2.0, stats.std_dev());
+    }
+
+    #[test]
+    fn stats_computes_the_sample_variance() {
+        let mut stats = Stats::new();
+        stats.push(3.0);
+        assert_eq!(0.0, stats.sample_variance());
+        for value in [5.0, 7.0] {
+            stats.push(value);
+        }
+        assert_eq!(4.0, stats.sample_variance());
+        assert_eq!(2.0, stats.sample_std_dev());
+    }
+
+    #[test]
+    fn histogram_counts_values_into_bins() {
+        let mut histogram: Histogram<4> = Histogram::new(0.0, 100.0).unwrap();
+        for value in [10.0, 30.0, 35.0, 99.0, 250.0, -5.0] {
+            histogram.record(value);
+        }
+        assert_eq!(&[2, 2, 0, 2], histogram.bins());
+        assert_eq!(Some(0), histogram.mode());
+        assert_eq!(25.0..50.0, histogram.bin_range(1));
+    }
+
+    #[test]
+    fn histogram_rejects_empty_ranges() {
+        assert_eq!(Err(ConfigError::EmptyRange), Histogram::<4>::new(10.0, 10.0));
+        assert_eq!(None, Histogram::<4>::new(0.0, 10.0).unwrap().mode());
+    }
+
+    #[test]
+    fn rolling_window_keeps_the_last_readings() {
+        let mut window: RollingWindow<u16, 3> = RollingWindow::new();
+        assert_eq!(None, window.median());
+        window.push(30);
+        window.push(10);
+        assert_eq!(Some(10), window.median());
+        window.push(20);
+        window.push(5);
+        assert_eq!(3, window.len());
+        assert_eq!(Some(10), window.median());
+        assert_eq!(Some(5), window.percentile(0.0));
+        assert_eq!(Some(20), window.percentile(1.0));
+    }
+
+    #[test]
+    fn empty_stats_are_zero() {
+        let stats = Stats::new();
+        assert_eq!(0, stats.count());
+        assert_eq!(0.0, stats.mean());
+        assert_eq!(0.0, stats.variance());
+    }
+
+    #[test]
+    fn reset_empties_the_accumulators() {
+        let mut stats = Stats::new();
+        stats.push(4.0);
+        stats.reset();
+        assert_eq!(Stats::new(), stats);
+        let mut histogram: Histogram<2> = Histogram::new(0.0, 10.0).unwrap();
+        histogram.record(3.0);
+        histogram.reset();
+        assert_eq!(None, histogram.mode());
+        let mut window: RollingWindow<u16, 2> = RollingWindow::new();
+        window.push(3);
+        window.reset();
+        assert!(window.is_empty());
+        assert_eq!(None, window.median());
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::SampleStats;
+
+    fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+
+    #[test]
+    fn sample_stats_implements_serde() {
+        assert_serde::<SampleStats<u16>>();
+    }
+
+    #[test]
+    fn sample_stats_round_trip_through_json() {
+        let stats = SampleStats { min: 10u16, max: 30u16, mean: 20.5, count: 4 };
+        let mut buffer = [0u8; 64];
+        let len = serde_json_core::to_slice(&stats, &mut buffer).unwrap();
+        let json = core::str::from_utf8(&buffer[..len]).unwrap();
+        assert_eq!(r#"{"min":10,"max":30,"mean":20.5,"count":4}"#, json);
+        let (decoded, _) = serde_json_core::from_str::<SampleStats<u16>>(json).unwrap();
+        assert_eq!(stats, decoded);
+    }
+}
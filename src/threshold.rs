@@ -0,0 +1,177 @@
+use crate::sensor::ConfigError;
+
+/// Change of the alert state reported by `Threshold::update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Crossing {
+    /// The density rose above the high limit.
+    Entered,
+    /// The density fell below the low limit.
+    Exited,
+}
+
+/// Alert state for a dust density with hysteresis, for example to drive a fan relay.
+///
+/// The alert is entered once the density rises above `high` and only exited once it falls below `low`,
+/// so readings hovering around a single setpoint do not toggle the state on every update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Threshold {
+    low: f32,
+    high: f32,
+    alert: bool,
+}
+
+impl Threshold {
+    /// Creates a threshold in the non-alert state.
+    ///
+    /// Returns `ConfigError::LowAboveHigh` if `high` is below `low`.
+    pub fn new(low: f32, high: f32) -> Result<Self, ConfigError> {
+        if low.is_nan() || high.is_nan() || high < low {
+            return Err(ConfigError::LowAboveHigh);
+        }
+        Ok(Self { low, high, alert: false })
+    }
+
+    /// Creates a threshold whose limits are `deadband` below and above `setpoint`.
+    ///
+    /// The deadband is typically a multiple of the sensor noise, see `Gp2y1014au::noise_floor_ug_m3`.
+    /// Returns `ConfigError::LowAboveHigh` if `deadband` is negative.
+    pub fn around(setpoint: f32, deadband: f32) -> Result<Self, ConfigError> {
+        Self::new(setpoint - deadband, setpoint + deadband)
+    }
+
+    /// Returns the limit below which the alert is exited.
+    pub fn low(&self) -> f32 {
+        self.low
+    }
+
+    /// Returns the limit above which the alert is entered.
+    pub fn high(&self) -> f32 {
+        self.high
+    }
+
+    /// Returns true while the alert is active.
+    pub fn is_alert(&self) -> bool {
+        self.alert
+    }
+
+    /// Leaves the alert state while keeping the limits, so the next density above `high` enters it again.
+    pub fn reset(&mut self) {
+        self.alert = false;
+    }
+
+    /// Updates the state with a new density and returns the crossing if the state changed.
+    pub fn update(&mut self, density: f32) -> Option<Crossing> {
+        if !self.alert && density > self.high {
+            self.alert = true;
+            Some(Crossing::Entered)
+        } else if self.alert && density < self.low {
+            self.alert = false;
+            Some(Crossing::Exited)
+        } else {
+            None
+        }
+    }
+}
+
+/// Detector for sudden rises of the dust density, for example when a stove is lit.
+///
+/// Each update computes the rate of change since the previous one in µg/m³ per second and reports whether it
+/// exceeds the threshold. Timestamps are in milliseconds, as from a `Clock` counting milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateDetector {
+    threshold: f32,
+    last: Option<(f32, u64)>,
+    rate: Option<f32>,
+}
+
+impl RateDetector {
+    /// Creates a detector reporting rises faster than `threshold` µg/m³ per second.
+    pub const fn new(threshold: f32) -> Self {
+        Self { threshold, last: None, rate: None }
+    }
+
+    /// Returns the rate in µg/m³ per second above which a rise is reported.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Returns the rate of change computed by the last update, or `None` before the second update.
+    pub fn rate(&self) -> Option<f32> {
+        self.rate
+    }
+
+    /// Updates the detector with a density taken at `ts_ms` and returns true if it rose faster than the threshold.
+    ///
+    /// The first update only stores the density. An update whose timestamp is not after the previous one
+    /// is ignored and returns false.
+    pub fn update(&mut self, density: f32, ts_ms: u64) -> bool {
+        if let Some((last_density, last_ts_ms)) = self.last {
+            if ts_ms <= last_ts_ms {
+                return false;
+            }
+            let elapsed_s = (ts_ms - last_ts_ms) as f32 / 1000.0;
+            self.rate = Some((density - last_density) / elapsed_s);
+        }
+        self.last = Some((density, ts_ms));
+        self.rate.is_some_and(|rate| rate > self.threshold)
+    }
+
+    /// Forgets the previous density and rate while keeping the threshold, for example after a gap in sampling.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.rate = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crossing, RateDetector, Threshold};
+    use crate::sensor::ConfigError;
+
+    #[test]
+    fn update_uses_hysteresis() {
+        let mut threshold = Threshold::new(50.0, 100.0).unwrap();
+        assert_eq!(None, threshold.update(90.0));
+        assert_eq!(Some(Crossing::Entered), threshold.update(110.0));
+        assert_eq!(None, threshold.update(60.0));
+        assert!(threshold.is_alert());
+        assert_eq!(Some(Crossing::Exited), threshold.update(40.0));
+        assert_eq!(None, threshold.update(90.0));
+        assert!(!threshold.is_alert());
+    }
+
+    #[test]
+    fn new_rejects_low_above_high() {
+        assert_eq!(Err(ConfigError::LowAboveHigh), Threshold::new(100.0, 50.0));
+        assert!(Threshold::new(50.0, 50.0).is_ok());
+    }
+
+    #[test]
+    fn reset_leaves_the_alert() {
+        let mut threshold = Threshold::new(50.0, 100.0).unwrap();
+        threshold.update(120.0);
+        threshold.reset();
+        assert!(!threshold.is_alert());
+        assert_eq!(Some(Crossing::Entered), threshold.update(120.0));
+    }
+
+    #[test]
+    fn around_spans_the_deadband() {
+        let threshold = Threshold::around(50.0, 5.0).unwrap();
+        assert_eq!(45.0, threshold.low());
+        assert_eq!(55.0, threshold.high());
+        assert_eq!(Err(ConfigError::LowAboveHigh), Threshold::around(50.0, -1.0));
+    }
+
+    #[test]
+    fn rate_detector_flags_fast_rises() {
+        let mut detector = RateDetector::new(5.0);
+        assert!(!detector.update(10.0, 0));
+        assert!(!detector.update(14.0, 1000));
+        assert_eq!(Some(4.0), detector.rate());
+        assert!(detector.update(20.0, 1500));
+        assert!(!detector.update(0.0, 1500));
+        assert!(!detector.update(10.0, 2500));
+    }
+}
@@ -0,0 +1,170 @@
+use embedded_hal::delay::DelayNs;
+use crate::sensor::ConfigError;
+
+/// Timing of the LED pulse and of the measurement cycle, in microseconds.
+///
+/// The LED is enabled for `pulse_width_us` and the output is sampled `sample_delay_us` after enabling it.
+/// Pulses are meant to repeat every `cycle_period_us`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timing {
+    /// Time between enabling the LED and sampling the output.
+    pub sample_delay_us: u16,
+    /// Total time the LED is enabled for each measurement.
+    pub pulse_width_us: u16,
+    /// Time between the start of two consecutive pulses.
+    pub cycle_period_us: u32,
+}
+
+impl Timing {
+    /// Timing recommended by the datasheet: sampling 0.28ms into a 0.32ms pulse, repeated every 10ms.
+    pub const fn datasheet() -> Self {
+        Self {
+            sample_delay_us: 280,
+            pulse_width_us: 320,
+            cycle_period_us: 10_000,
+        }
+    }
+
+    /// Creates a timing after checking it with `validate`.
+    pub fn new(sample_delay_us: u16, pulse_width_us: u16, cycle_period_us: u32) -> Result<Self, ConfigError> {
+        let timing = Self { sample_delay_us, pulse_width_us, cycle_period_us };
+        timing.validate()?;
+        Ok(timing)
+    }
+
+    /// Returns `ConfigError::InvalidTiming` unless `sample_delay_us < pulse_width_us < cycle_period_us`,
+    /// so the output is sampled before the end of the pulse and the pulse ends before the next one.
+    ///
+    /// This is a `const fn`, so timings defined as constants can be checked at compile time:
+    ///
+    /// ```
+    /// # use gp2y1014au::timing::Timing;
+    /// const TIMING: Timing = Timing { sample_delay_us: 250, pulse_width_us: 300, cycle_period_us: 10_000 };
+    /// const _: () = assert!(TIMING.validate().is_ok());
+    /// ```
+    pub const fn validate(&self) -> Result<(), ConfigError> {
+        if self.sample_delay_us >= self.pulse_width_us || self.pulse_width_us as u32 >= self.cycle_period_us {
+            return Err(ConfigError::InvalidTiming);
+        }
+        Ok(())
+    }
+
+    /// Returns the time the LED is kept enabled after sampling the output.
+    pub const fn settling_delay_us(&self) -> u16 {
+        self.pulse_width_us.saturating_sub(self.sample_delay_us)
+    }
+
+    /// Returns the time between the start of two measurements, the cycle period or the pulse width if it is longer.
+    pub const fn measurement_period(&self) -> core::time::Duration {
+        core::time::Duration::from_micros(self.measurement_period_us() as u64)
+    }
+
+    /// Returns the fraction of the time the LED is enabled, from 0.0 to 1.0.
+    ///
+    /// A cycle shorter than the pulse counts as the LED being on all the time.
+    pub const fn led_duty_cycle(&self) -> f32 {
+        let period_us = self.measurement_period_us();
+        if period_us == 0 {
+            return 1.0;
+        }
+        self.pulse_width_us as f32 / period_us as f32
+    }
+
+    /// Returns the highest number of measurements per second, the inverse of `measurement_period`.
+    ///
+    /// Returns `f32::INFINITY` if both the pulse width and the cycle period are 0.
+    pub const fn max_sample_rate_hz(&self) -> f32 {
+        let period_us = self.measurement_period_us();
+        if period_us == 0 {
+            return f32::INFINITY;
+        }
+        1_000_000.0 / period_us as f32
+    }
+
+    /// Returns the measurement period in microseconds, see `measurement_period`.
+    const fn measurement_period_us(&self) -> u32 {
+        let pulse_width_us = self.pulse_width_us as u32;
+        if self.cycle_period_us > pulse_width_us { self.cycle_period_us } else { pulse_width_us }
+    }
+}
+
+/// Defaults to the timing recommended by the datasheet, see `Timing::datasheet`.
+impl Default for Timing {
+    fn default() -> Self {
+        Self::datasheet()
+    }
+}
+
+const _: () = assert!(Timing::datasheet().validate().is_ok());
+
+/// Estimates the average current in milliamperes drawn by the sensor when pulsed with `timing`.
+///
+/// The sensor draws `led_current_ma` for `pulse_width_us` of every cycle and `idle_current_ma` for the
+/// rest of it, the result is the time-weighted average of both. A cycle shorter than the pulse counts as
+/// the LED being on all the time.
+pub fn estimated_average_current_ma(timing: &Timing, led_current_ma: f32, idle_current_ma: f32) -> f32 {
+    let duty_cycle = timing.led_duty_cycle();
+    led_current_ma * duty_cycle + idle_current_ma * (1.0 - duty_cycle)
+}
+
+/// Delay which returns immediately, for HALs without a microsecond delay source.
+///
+/// A sensor using it does not time the LED pulse, so the caller has to make sure the ADC conversion
+/// completes about 280µs after the LED was enabled, for example with a slow enough ADC clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "eh02")]
+impl embedded_hal_02::blocking::delay::DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimated_average_current_ma, Timing};
+    use crate::sensor::ConfigError;
+
+    #[test]
+    fn datasheet_timing_is_valid() {
+        assert_eq!(Ok(Timing::datasheet()), Timing::new(280, 320, 10_000));
+        assert_eq!(40, Timing::datasheet().settling_delay_us());
+        assert_eq!(Timing::datasheet(), Timing::default());
+    }
+
+    #[test]
+    fn new_rejects_sampling_after_the_pulse() {
+        assert_eq!(Err(ConfigError::InvalidTiming), Timing::new(320, 320, 10_000));
+        assert_eq!(Err(ConfigError::InvalidTiming), Timing::new(400, 320, 10_000));
+    }
+
+    #[test]
+    fn new_rejects_pulses_longer_than_the_cycle() {
+        assert_eq!(Err(ConfigError::InvalidTiming), Timing::new(280, 320, 320));
+    }
+
+    #[test]
+    fn average_current_is_weighted_by_the_duty_cycle() {
+        let timing = Timing::new(280, 320, 10_000).unwrap();
+        let current = estimated_average_current_ma(&timing, 20.0, 1.0);
+        assert!((current - 1.608).abs() < 1e-4, "unexpected current {}", current);
+        let always_on = Timing { cycle_period_us: 0, ..timing };
+        assert_eq!(20.0, estimated_average_current_ma(&always_on, 20.0, 1.0));
+    }
+
+    #[test]
+    fn duty_cycle_and_rate_follow_the_cycle_period() {
+        const TIMING: Timing = Timing::datasheet();
+        const RATE_HZ: f32 = TIMING.max_sample_rate_hz();
+        assert_eq!(core::time::Duration::from_millis(10), TIMING.measurement_period());
+        assert_eq!(0.032, TIMING.led_duty_cycle());
+        assert_eq!(100.0, RATE_HZ);
+        let always_on = Timing { cycle_period_us: 0, ..TIMING };
+        assert_eq!(core::time::Duration::from_micros(320), always_on.measurement_period());
+        assert_eq!(1.0, always_on.led_duty_cycle());
+    }
+}
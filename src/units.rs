@@ -0,0 +1,138 @@
+//! Unit newtypes, so a voltage cannot be passed where a density or a voltage in volts is expected.
+//!
+//! Both types convert from and into their bare number with `From`, and support the arithmetic
+//! which keeps the unit, such as adding two voltages or scaling a density. Voltages are `f32`
+//! since the sensor output is rarely a whole number of millivolts.
+
+use core::ops::{Add, Div, Mul, Sub};
+
+/// A voltage in millivolts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Millivolts(pub f32);
+
+impl From<f32> for Millivolts {
+    fn from(millivolts: f32) -> Self {
+        Millivolts(millivolts)
+    }
+}
+
+impl From<u16> for Millivolts {
+    fn from(millivolts: u16) -> Self {
+        Millivolts(millivolts.into())
+    }
+}
+
+impl From<Millivolts> for f32 {
+    fn from(millivolts: Millivolts) -> Self {
+        millivolts.0
+    }
+}
+
+impl Add for Millivolts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Millivolts(self.0 + other.0)
+    }
+}
+
+impl Sub for Millivolts {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Millivolts(self.0 - other.0)
+    }
+}
+
+impl Mul<f32> for Millivolts {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Millivolts(self.0 * factor)
+    }
+}
+
+impl Div<f32> for Millivolts {
+    type Output = Self;
+
+    fn div(self, divisor: f32) -> Self {
+        Millivolts(self.0 / divisor)
+    }
+}
+
+/// A dust density in µg/m³.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MicrogramsPerM3(pub f32);
+
+impl From<f32> for MicrogramsPerM3 {
+    fn from(ug_m3: f32) -> Self {
+        MicrogramsPerM3(ug_m3)
+    }
+}
+
+impl From<MicrogramsPerM3> for f32 {
+    fn from(density: MicrogramsPerM3) -> Self {
+        density.0
+    }
+}
+
+impl Add for MicrogramsPerM3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        MicrogramsPerM3(self.0 + other.0)
+    }
+}
+
+impl Sub for MicrogramsPerM3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        MicrogramsPerM3(self.0 - other.0)
+    }
+}
+
+impl Mul<f32> for MicrogramsPerM3 {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        MicrogramsPerM3(self.0 * factor)
+    }
+}
+
+impl Div<f32> for MicrogramsPerM3 {
+    type Output = Self;
+
+    fn div(self, divisor: f32) -> Self {
+        MicrogramsPerM3(self.0 / divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MicrogramsPerM3, Millivolts};
+
+    #[test]
+    fn millivolts_convert_and_keep_the_unit_through_arithmetic() {
+        assert_eq!(Millivolts(3300.0), 3300u16.into());
+        assert_eq!(Millivolts(1650.5), 1650.5.into());
+        assert_eq!(3300.0, f32::from(Millivolts(3300.0)));
+        assert_eq!(Millivolts(700.0), Millivolts(600.0) + Millivolts(100.0));
+        assert_eq!(Millivolts(-100.0), Millivolts(100.0) - Millivolts(200.0));
+        assert_eq!(Millivolts(1650.0), Millivolts(3300.0) / 2.0);
+        assert_eq!(Millivolts(6600.0), Millivolts(3300.0) * 2.0);
+    }
+
+    #[test]
+    fn micrograms_per_m3_keep_the_unit_through_arithmetic() {
+        let density = MicrogramsPerM3(10.0) + MicrogramsPerM3(30.0);
+        assert_eq!(MicrogramsPerM3(20.0), density / 2.0);
+        assert_eq!(MicrogramsPerM3(80.0), density * 2.0);
+        assert_eq!(MicrogramsPerM3(-20.0), MicrogramsPerM3(10.0) - MicrogramsPerM3(30.0));
+        assert_eq!(40.0, f32::from(density));
+    }
+}